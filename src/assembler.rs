@@ -0,0 +1,258 @@
+// Tokenizer and token cursor for Aleo instructions source, the text counterpart of `ByteCode`
+use crate::error::ParseError;
+use alloc::{string::{String, ToString}, vec::Vec};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    // Keyword, identifier, register, type or literal, e.g. `add.w`, `r0.a0`, `u64.public`, `-5i8`
+    Word(String),
+    // Quoted string literal, already unescaped
+    String(String),
+    Colon,
+    Semicolon,
+    LeftBracket,
+    RightBracket,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Parser {
+    tokens: Vec<Token>,
+    idx: usize,
+    // Line and column just past the end of the source, for errors on missing tokens
+    end: (usize, usize),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/' | '-')
+}
+
+pub fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Parser {
+    pub fn new(source: &str) -> Result<Self, ParseError> {
+        let mut tokens = Vec::new();
+        let (mut line, mut column) = (1, 1);
+        let mut chars = source.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let (start_line, start_column) = (line, column);
+            let error = |message: &str| ParseError { line: start_line, column: start_column, message: message.to_string() };
+            // Consume one character, keeping track of lines and columns
+            let mut bump = |chars: &mut core::iter::Peekable<core::str::Chars>| {
+                let c = chars.next();
+                if c == Some('\n') {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+                c
+            };
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    bump(&mut chars);
+                    continue;
+                },
+                '/' => {
+                    bump(&mut chars);
+                    match bump(&mut chars) {
+                        Some('/') => while !matches!(chars.peek(), None | Some('\n')) {
+                            bump(&mut chars);
+                        },
+                        Some('*') => loop {
+                            match bump(&mut chars) {
+                                Some('*') if chars.peek() == Some(&'/') => {
+                                    bump(&mut chars);
+                                    break;
+                                },
+                                Some(_) => {},
+                                None => return Err(error("unterminated comment")),
+                            }
+                        },
+                        _ => return Err(error("unexpected `/`")),
+                    }
+                    continue;
+                },
+                ':' => { bump(&mut chars); TokenKind::Colon },
+                ';' => { bump(&mut chars); TokenKind::Semicolon },
+                '[' => { bump(&mut chars); TokenKind::LeftBracket },
+                ']' => { bump(&mut chars); TokenKind::RightBracket },
+                '"' => {
+                    bump(&mut chars);
+                    let mut s = String::new();
+                    loop {
+                        match bump(&mut chars) {
+                            Some('"') => break,
+                            Some('\\') => s.push(match bump(&mut chars) {
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                Some('n') => '\n',
+                                Some('r') => '\r',
+                                Some('t') => '\t',
+                                Some('0') => '\0',
+                                Some('u') if chars.peek() == Some(&'{') => {
+                                    bump(&mut chars);
+                                    let mut hex = String::new();
+                                    while let Some(h) = bump(&mut chars) {
+                                        if h == '}' {
+                                            break;
+                                        }
+                                        hex.push(h);
+                                    }
+                                    u32::from_str_radix(&hex, 16).ok()
+                                        .and_then(char::from_u32)
+                                        .ok_or_else(|| error("invalid unicode escape in string"))?
+                                },
+                                _ => return Err(error("invalid escape in string")),
+                            }),
+                            Some(c) => s.push(c),
+                            None => return Err(error("unterminated string")),
+                        }
+                    }
+                    TokenKind::String(s)
+                },
+                c if is_word_char(c) => {
+                    let mut word = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !is_word_char(c) {
+                            break;
+                        }
+                        word.push(c);
+                        bump(&mut chars);
+                    }
+                    TokenKind::Word(word)
+                },
+                c => return Err(error(&format!("unexpected character `{}`", c))),
+            };
+            tokens.push(Token { kind, line: start_line, column: start_column });
+        }
+        Ok(Self { tokens, idx: 0, end: (line, column) })
+    }
+
+    // Index of the next token, to report errors on a construct after it was consumed
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idx >= self.tokens.len()
+    }
+
+    pub fn error_at(&self, position: usize, message: &str) -> ParseError {
+        let (line, column) = match self.tokens.get(position) {
+            Some(token) => (token.line, token.column),
+            None => self.end,
+        };
+        ParseError { line, column, message: message.to_string() }
+    }
+
+    pub fn error(&self, message: &str) -> ParseError {
+        self.error_at(self.idx, message)
+    }
+
+    pub fn peek(&self) -> Option<&TokenKind> {
+        self.peek_nth(0)
+    }
+
+    pub fn peek_nth(&self, n: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.idx + n).map(|t| &t.kind)
+    }
+
+    pub fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(TokenKind::Word(w)) => Some(w),
+            _ => None,
+        }
+    }
+
+    pub fn next(&mut self) -> Option<TokenKind> {
+        let token = self.tokens.get(self.idx).map(|t| t.kind.clone());
+        if token.is_some() {
+            self.idx += 1;
+        }
+        token
+    }
+
+    pub fn next_word(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(TokenKind::Word(w)) => {
+                let w = w.clone();
+                self.idx += 1;
+                Ok(w)
+            },
+            _ => Err(self.error("expected a word")),
+        }
+    }
+
+    pub fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.peek() == Some(kind);
+        if found {
+            self.idx += 1;
+        }
+        found
+    }
+
+    pub fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.peek_word() == Some(word);
+        if found {
+            self.idx += 1;
+        }
+        found
+    }
+
+    pub fn expect(&mut self, kind: &TokenKind) -> Result<(), ParseError> {
+        if self.eat(kind) {
+            return Ok(());
+        }
+        let expected = match kind {
+            TokenKind::Word(w) => format!("expected `{}`", w),
+            TokenKind::String(_) => "expected a string".to_string(),
+            TokenKind::Colon => "expected `:`".to_string(),
+            TokenKind::Semicolon => "expected `;`".to_string(),
+            TokenKind::LeftBracket => "expected `[`".to_string(),
+            TokenKind::RightBracket => "expected `]`".to_string(),
+        };
+        Err(self.error(&expected))
+    }
+
+    pub fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        self.expect(&TokenKind::Word(word.to_string()))
+    }
+
+    pub fn identifier(&mut self) -> Result<String, ParseError> {
+        let start = self.position();
+        let word = self.next_word()?;
+        if !is_identifier(&word) {
+            return Err(self.error_at(start, &format!("`{}` is not a valid identifier", word)));
+        }
+        Ok(word)
+    }
+
+    // `name.network`
+    pub fn programid(&mut self) -> Result<(String, String), ParseError> {
+        let start = self.position();
+        let word = self.next_word()?;
+        split_programid(&word).ok_or_else(|| self.error_at(start, &format!("`{}` is not a valid program id", word)))
+    }
+}
+
+pub fn split_programid(s: &str) -> Option<(String, String)> {
+    let (name, network) = s.split_once('.')?;
+    (is_identifier(name) && is_identifier(network)).then(|| (name.to_string(), network.to_string()))
+}
+
+// `name.network/resource`
+pub fn split_locator(s: &str) -> Option<(String, String, String)> {
+    let (program, resource) = s.split_once('/')?;
+    let (name, network) = split_programid(program)?;
+    is_identifier(resource).then(|| (name, network, resource.to_string()))
+}
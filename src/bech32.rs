@@ -0,0 +1,237 @@
+// Bech32m (BIP-350) encoding, as used by Aleo for addresses
+use core::fmt;
+use alloc::{string::String, vec::Vec};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const BECH32M_CONST: u32 = 0x2bc830a3;
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+
+pub const ADDRESS_PREFIX: &str = "aleo";
+pub const ADDRESS_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bech32Error {
+    // Upper and lower case characters are mixed
+    MixedCase,
+    // No `1` separating the human readable part from the data
+    MissingSeparator,
+    // The human readable part is empty or holds a character outside of 33..=126
+    InvalidHrp,
+    // Data character outside of the bech32 charset, with its position
+    InvalidChar(usize),
+    // The string is longer than 90 characters, its data part shorter than the checksum
+    // or it does not hold the expected number of bytes
+    InvalidLength,
+    // The checksum does not match the bech32m constant
+    InvalidChecksum,
+    // Non-zero bits are left over when converting back to bytes
+    InvalidPadding,
+    // The human readable part is not the expected one
+    UnexpectedHrp(String),
+}
+
+impl fmt::Display for Bech32Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedCase => write!(f, "mixed case bech32 string"),
+            Self::MissingSeparator => write!(f, "missing bech32 separator"),
+            Self::InvalidHrp => write!(f, "invalid bech32 human readable part"),
+            Self::InvalidChar(i) => write!(f, "invalid bech32 character at position {}", i),
+            Self::InvalidLength => write!(f, "invalid bech32 string length"),
+            Self::InvalidChecksum => write!(f, "invalid bech32m checksum"),
+            Self::InvalidPadding => write!(f, "invalid bech32 padding"),
+            Self::UnexpectedHrp(hrp) => write!(f, "unexpected bech32 prefix `{}`", hrp),
+        }
+    }
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut chk: u32 = 1;
+    for v in values {
+        let b = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes().map(|b| b >> 5).chain(core::iter::once(0)).chain(hrp.bytes().map(|b| b & 31))
+}
+
+fn create_checksum(hrp: &str, data: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let values = hrp_expand(hrp).chain(data.iter().copied()).chain([0u8; CHECKSUM_LENGTH]);
+    let m = polymod(values) ^ BECH32M_CONST;
+    let mut checksum = [0u8; CHECKSUM_LENGTH];
+    for (i, c) in checksum.iter_mut().enumerate() {
+        *c = ((m >> (5 * (5 - i))) & 31) as u8;
+    }
+    checksum
+}
+
+// Regroup bits, e.g. 8-bit bytes into 5-bit words and back
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Bech32Error> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1 << to) - 1;
+    let max_acc = (1 << (from + to - 1)) - 1;
+    let mut o = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for value in data {
+        acc = ((acc << from) | *value as u32) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            o.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            o.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return Err(Bech32Error::InvalidPadding);
+    }
+    Ok(o)
+}
+
+// Encode bytes as a bech32m string with the given human readable part
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    encode_words(hrp, &convert_bits(data, 8, 5, true).unwrap_or_default())
+}
+
+// Bech32m string of already regrouped 5-bit words
+fn encode_words(hrp: &str, words: &[u8]) -> String {
+    let checksum = create_checksum(hrp, words);
+    let mut o = String::with_capacity(hrp.len() + 1 + words.len() + CHECKSUM_LENGTH);
+    o.push_str(hrp);
+    o.push('1');
+    for w in words.iter().chain(checksum.iter()) {
+        o.push(CHARSET[*w as usize] as char);
+    }
+    o
+}
+
+// Human readable part and 5-bit data words of a bech32m string, checksum removed
+fn decode_words(s: &str) -> Result<(String, Vec<u8>), Bech32Error> {
+    let has_lower = s.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = s.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Bech32Error::MixedCase);
+    }
+    if s.len() > MAX_LENGTH {
+        return Err(Bech32Error::InvalidLength);
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1').ok_or(Bech32Error::MissingSeparator)?;
+    let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+    if hrp.is_empty() || hrp.bytes().any(|b| !(33..=126).contains(&b)) {
+        return Err(Bech32Error::InvalidHrp);
+    }
+    if data.len() < CHECKSUM_LENGTH {
+        return Err(Bech32Error::InvalidLength);
+    }
+    let words = data
+        .bytes()
+        .enumerate()
+        .map(|(i, b)| {
+            CHARSET.iter()
+                .position(|c| *c == b)
+                .map(|p| p as u8)
+                .ok_or(Bech32Error::InvalidChar(separator + 1 + i))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    if polymod(hrp_expand(hrp).chain(words.iter().copied())) != BECH32M_CONST {
+        return Err(Bech32Error::InvalidChecksum);
+    }
+    Ok((String::from(hrp), words[..words.len() - CHECKSUM_LENGTH].to_vec()))
+}
+
+// Decode a bech32m string into its human readable part and bytes
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), Bech32Error> {
+    let (hrp, words) = decode_words(s)?;
+    Ok((hrp, convert_bits(&words, 5, 8, false)?))
+}
+
+// `aleo1...` form of an address given as its 32 little-endian bytes
+pub fn encode_address(bytes: &[u8]) -> String {
+    encode(ADDRESS_PREFIX, bytes)
+}
+
+// 32 little-endian bytes of an `aleo1...` address
+pub fn decode_address(address: &str) -> Result<Vec<u8>, Bech32Error> {
+    let (hrp, bytes) = decode(address)?;
+    if hrp != ADDRESS_PREFIX {
+        return Err(Bech32Error::UnexpectedHrp(hrp));
+    }
+    if bytes.len() != ADDRESS_LENGTH {
+        return Err(Bech32Error::InvalidLength);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Valid bech32m strings from BIP-350
+    const VALID: &[&str] = &[
+        "A1LQFN3A",
+        "a1lqfn3a",
+        "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+        "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+        "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+        "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        "?1v759aa",
+    ];
+
+    #[test]
+    fn bip350_valid() {
+        for s in VALID {
+            let (hrp, words) = decode_words(s).unwrap_or_else(|e| panic!("`{}`: {}", s, e));
+            assert_eq!(hrp, s[..s.rfind('1').unwrap()].to_ascii_lowercase());
+            let encoded = encode_words(&hrp, &words);
+            assert_eq!(encoded, s.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn bip350_invalid() {
+        let invalid = [
+            ("\u{20}1xj0phk", Bech32Error::InvalidHrp),
+            ("\u{7f}1g6xzxy", Bech32Error::InvalidHrp),
+            ("\u{80}1vctc34", Bech32Error::InvalidHrp),
+            ("an84characterslonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11d6pts4", Bech32Error::InvalidLength),
+            ("qyrz8wqd2c9m", Bech32Error::MissingSeparator),
+            ("1qyrz8wqd2c9m", Bech32Error::InvalidHrp),
+            ("y1b0jsk6g", Bech32Error::InvalidChar(2)),
+            ("lt1igcx5c0", Bech32Error::InvalidChar(3)),
+            ("in1muywd", Bech32Error::InvalidLength),
+            ("mm1crxm3i", Bech32Error::InvalidChar(8)),
+            ("au1s5cgom", Bech32Error::InvalidChar(7)),
+            ("M1VUXWEZ", Bech32Error::InvalidChecksum),
+            ("16plkw9", Bech32Error::InvalidHrp),
+            ("1p2gdwpf", Bech32Error::InvalidHrp),
+            // Valid bech32 (BIP-173) but not bech32m
+            ("a12uel5l", Bech32Error::InvalidChecksum),
+        ];
+        for (s, error) in invalid {
+            assert_eq!(decode(s), Err(error), "`{}`", s);
+        }
+    }
+
+    #[test]
+    fn address_round_trip() {
+        let address = "aleo1rhgdu77hgyqd3xjj8ucu3jj9r2krwz6mnzyd80gncr5fxcwlh5rsvzp9px";
+        let bytes = decode_address(address).expect("invalid address");
+        assert_eq!(bytes.len(), ADDRESS_LENGTH);
+        assert_eq!(encode_address(&bytes), address);
+        assert_eq!(decode_address(&address.to_ascii_uppercase()), Ok(bytes));
+        assert_eq!(decode_address("a1lqfn3a"), Err(Bech32Error::UnexpectedHrp("a".into())));
+    }
+}
@@ -1,76 +1,136 @@
-use alloc::{vec::Vec};
+use alloc::string::String;
+use crate::error::{DecodeError, Location};
+use crate::components::commands::CommandLayout;
 
-#[derive(Default, Debug)]
-pub struct ByteCode {
-    bytes: Vec<u8>,
-    pub idx: usize,
+// Forward-only cursor over a borrowed program, every read is bounds-checked
+#[derive(Default, Debug, Clone)]
+pub struct ByteCode<'a> {
+    bytes: &'a [u8],
+    idx: usize,
+    context: String,
+    // Numbering of the finalize commands
+    command_layout: CommandLayout,
 }
 
-impl ByteCode {
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes, idx: 0 }
+impl<'a> ByteCode<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, idx: 0, context: String::new(), command_layout: CommandLayout::Current }
+    }
+
+    // Offset of the next byte to be read
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+
+    // Number of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.idx
+    }
+
+    // Move the cursor to an absolute offset, the end of the program included
+    pub fn seek(&mut self, offset: usize) -> Result<(), DecodeError> {
+        if offset > self.bytes.len() {
+            return Err(DecodeError::UnexpectedEof(self.location_at(offset)));
+        }
+        self.idx = offset;
+        Ok(())
+    }
+
+    // Name of the component currently being decoded, attached to errors
+    pub fn set_context(&mut self, context: &str) {
+        self.context.clear();
+        self.context.push_str(context);
+    }
+
+    pub fn set_command_layout(&mut self, layout: CommandLayout) {
+        self.command_layout = layout;
+    }
+
+    pub fn command_layout(&self) -> CommandLayout {
+        self.command_layout
+    }
+
+    // Location of the next byte to be read
+    pub fn location(&self) -> Location {
+        self.location_at(self.idx)
+    }
+
+    // Location of an already consumed byte
+    pub fn location_at(&self, offset: usize) -> Location {
+        Location { offset, context: self.context.clone() }
     }
 
     // Peek one byte as unsigned int in little endian
-    pub fn peek(&self) -> u8 {
-        self.bytes[0]
+    pub fn peek(&self) -> Result<u8, DecodeError> {
+        self.bytes.get(self.idx).copied().ok_or_else(|| DecodeError::UnexpectedEof(self.location()))
+    }
+
+    // Read n bytes without copying them
+    pub fn read_n(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if n > self.remaining() {
+            return Err(DecodeError::UnexpectedEof(self.location_at(self.bytes.len())));
+        }
+        let slice = &self.bytes[self.idx..self.idx + n];
+        self.idx += n;
+        Ok(slice)
+    }
+
+    // Read a fixed number of bytes
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_n(N)?);
+        Ok(array)
     }
 
     // Read one byte as unsigned int in little endian
-    pub fn read_u8(&mut self) -> u8 {
-        // let x = self.bytes[self.idx];
-        // self.idx += 1;
-        // x
-        self.bytes.remove(0)
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let x = self.peek()?;
+        self.idx += 1;
+        Ok(x)
     }
 
     // Read two bytes as unsigned int in little endian
-    pub fn read_u16(&mut self) -> u16 {
-        (self.read_u8() as u16) + ((self.read_u8() as u16) << 8)
+    pub fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
     }
 
     // Read four bytes as unsigned int in little endian
-    pub fn read_u32(&mut self) -> u32 {
-        (self.read_u16() as u32) + ((self.read_u16() as u32) << 16)
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
     }
 
     // Read eight bytes as unsigned int in little endian
-    pub fn read_u64(&mut self) -> u64 {
-        (self.read_u32() as u64) + ((self.read_u32() as u64) << 32)
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
     }
 
     // Read 16 bytes as unsigned int in little endian
-    pub fn read_u128(&mut self) -> u128 {
-        (self.read_u64() as u128) + ((self.read_u64() as u128) << 64)
+    pub fn read_u128(&mut self) -> Result<u128, DecodeError> {
+        Ok(u128::from_le_bytes(self.read_array()?))
     }
 
     // Read one byte as signed int in little endian
-    pub fn read_i8(&mut self) -> i8 {
-        self.read_u8() as i8
+    pub fn read_i8(&mut self) -> Result<i8, DecodeError> {
+        Ok(self.read_u8()? as i8)
     }
 
     // Read two bytes as signed int in little endian
-    pub fn read_i16(&mut self) -> i16 {
-        self.read_u16() as i16
-    }
-    
-    // Read four bytes as signed int in little endian
-    pub fn read_i32(&mut self) -> i32 {
-        self.read_u32() as i32
+    pub fn read_i16(&mut self) -> Result<i16, DecodeError> {
+        Ok(i16::from_le_bytes(self.read_array()?))
     }
 
-    // Read eight bytes as signed int in little endian
-    pub fn read_i64(&mut self) -> i64 {
-        self.read_u64() as i64
+    // Read four bytes as signed int in little endian
+    pub fn read_i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
     }
 
     // Read eight bytes as signed int in little endian
-    pub fn read_i128(&mut self) -> i128 {
-        self.read_u128() as i128
+    pub fn read_i64(&mut self) -> Result<i64, DecodeError> {
+        Ok(i64::from_le_bytes(self.read_array()?))
     }
 
-    // Read n bytes
-    pub fn read_n(&mut self, n: usize) -> Vec<u8> {
-        (0..n).map(|_| self.read_u8()).collect()
+    // Read 16 bytes as signed int in little endian
+    pub fn read_i128(&mut self) -> Result<i128, DecodeError> {
+        Ok(i128::from_le_bytes(self.read_array()?))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,249 @@
+use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
+use crate::output::Assembly;
+use crate::leo::{lower::Lower, printer};
+use crate::inference::RegisterTypes;
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
+use crate::util;
+use super::instructions::{ Instruction, Operand };
+use super::registers::Register;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Statement of a finalize block: either a plain instruction or an operation on a mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    Instruction(Instruction),
+    // contains account[r0] into r1;
+    Contains { mapping: String, key: Operand, destination: Register },
+    // decrement account[r0] by r1;
+    Decrement { mapping: String, key: Operand, value: Operand },
+    // get account[r0] into r1;
+    Get { mapping: String, key: Operand, destination: Register },
+    // get.or_use account[r0] 0u64 into r1;
+    GetOrUse { mapping: String, key: Operand, default: Operand, destination: Register },
+    // increment account[r0] by r1;
+    Increment { mapping: String, key: Operand, value: Operand },
+    // remove account[r0];
+    Remove { mapping: String, key: Operand },
+    // set r1 into account[r0];
+    Set { mapping: String, key: Operand, value: Operand },
+}
+
+// Numbering of the commands in bytecode. snarkVM renumbered them when it replaced `increment`
+// and `decrement` with `contains` and `remove`, and renamed `get.or_init` to `get.or_use`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommandLayout {
+    #[default]
+    Current,
+    Legacy,
+}
+
+impl CommandLayout {
+    // Keyword of each tag, the instruction tag 0 excluded
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Self::Current => &["contains", "get", "get.or_use", "rand.chacha", "remove", "set"],
+            Self::Legacy => &["decrement", "get", "get.or_init", "increment", "set"],
+        }
+    }
+
+    fn tag(&self, keyword: &str) -> Option<u8> {
+        self.keywords().iter().position(|k| *k == keyword).map(|n| n as u8 + 1)
+    }
+}
+
+impl Command {
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let start = bytes.position();
+        let tag = bytes.read_u8()?;
+        if tag == 0 {
+            return Ok(Self::Instruction(Instruction::read(bytes)?));
+        }
+        let keyword = match bytes.command_layout().keywords().get(tag as usize - 1) {
+            Some(&"rand.chacha") => return Err(DecodeError::Unsupported("rand.chacha command", bytes.location_at(start))),
+            Some(k) => *k,
+            None => return Err(DecodeError::UnknownCommandTag(tag, bytes.location_at(start))),
+        };
+        let mapping = util::read_identifier(bytes)?;
+        let key = Operand::read(bytes)?;
+        Ok(match keyword {
+            "contains" => Self::Contains { mapping, key, destination: Register::read(bytes)? },
+            "decrement" => Self::Decrement { mapping, key, value: Operand::read(bytes)? },
+            "get" => Self::Get { mapping, key, destination: Register::read(bytes)? },
+            "get.or_use" | "get.or_init" => Self::GetOrUse { mapping, key, default: Operand::read(bytes)?, destination: Register::read(bytes)? },
+            "increment" => Self::Increment { mapping, key, value: Operand::read(bytes)? },
+            "remove" => Self::Remove { mapping, key },
+            _ => Self::Set { mapping, key, value: Operand::read(bytes)? },
+        })
+    }
+
+    // Keyword the command starts with in `layout`, `None` for plain instructions
+    pub fn keyword(&self, layout: CommandLayout) -> Option<&'static str> {
+        Some(match self {
+            Self::Instruction(_) => return None,
+            Self::Contains { .. } => "contains",
+            Self::Decrement { .. } => "decrement",
+            Self::Get { .. } => "get",
+            Self::GetOrUse { .. } if layout == CommandLayout::Legacy => "get.or_init",
+            Self::GetOrUse { .. } => "get.or_use",
+            Self::Increment { .. } => "increment",
+            Self::Remove { .. } => "remove",
+            Self::Set { .. } => "set",
+        })
+    }
+
+    // Layout a command starting with `keyword` is only valid in
+    pub(crate) fn layout_of(keyword: &str) -> Option<CommandLayout> {
+        match keyword {
+            "contains" | "remove" | "get.or_use" => Some(CommandLayout::Current),
+            "decrement" | "increment" | "get.or_init" => Some(CommandLayout::Legacy),
+            _ => None,
+        }
+    }
+
+    // Whether the next statement is a command
+    pub(crate) fn is_next(p: &Parser) -> bool {
+        matches!(p.peek_word(), Some("contains" | "decrement" | "get" | "get.or_use" | "get.or_init" | "increment" | "remove" | "set")) || Instruction::is_next(p)
+    }
+
+    // `account[r0]`
+    fn parse_access(p: &mut Parser) -> Result<(String, Operand), ParseError> {
+        let mapping = p.identifier()?;
+        p.expect(&TokenKind::LeftBracket)?;
+        let key = Operand::parse(p)?;
+        p.expect(&TokenKind::RightBracket)?;
+        Ok((mapping, key))
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let keyword = match p.peek_word() {
+            Some(k @ ("contains" | "decrement" | "get" | "get.or_use" | "get.or_init" | "increment" | "remove" | "set")) => String::from(k),
+            _ => return Ok(Self::Instruction(Instruction::parse(p)?)),
+        };
+        p.next();
+        let command = match keyword.as_str() {
+            "contains" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                p.expect_word("into")?;
+                Self::Contains { mapping, key, destination: Register::parse(p)? }
+            },
+            "decrement" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                p.expect_word("by")?;
+                Self::Decrement { mapping, key, value: Operand::parse(p)? }
+            },
+            "get" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                p.expect_word("into")?;
+                Self::Get { mapping, key, destination: Register::parse(p)? }
+            },
+            "get.or_use" | "get.or_init" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                let default = Operand::parse(p)?;
+                p.expect_word("into")?;
+                Self::GetOrUse { mapping, key, default, destination: Register::parse(p)? }
+            },
+            "increment" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                p.expect_word("by")?;
+                Self::Increment { mapping, key, value: Operand::parse(p)? }
+            },
+            "remove" => {
+                let (mapping, key) = Self::parse_access(p)?;
+                Self::Remove { mapping, key }
+            },
+            _ => {
+                let value = Operand::parse(p)?;
+                p.expect_word("into")?;
+                let (mapping, key) = Self::parse_access(p)?;
+                Self::Set { mapping, key, value }
+            },
+        };
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(command)
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>, layout: CommandLayout) -> Result<(), EncodeError> {
+        let (keyword, mapping, key) = match (self, self.keyword(layout)) {
+            (Self::Instruction(instruction), _) => {
+                bytes.push(0);
+                return instruction.write(bytes);
+            },
+            (Self::Contains { mapping, key, .. }
+            | Self::Decrement { mapping, key, .. }
+            | Self::Get { mapping, key, .. }
+            | Self::GetOrUse { mapping, key, .. }
+            | Self::Increment { mapping, key, .. }
+            | Self::Remove { mapping, key }
+            | Self::Set { mapping, key, .. }, keyword) => (keyword.unwrap_or_default(), mapping, key),
+        };
+        let tag = layout.tag(keyword).ok_or_else(|| EncodeError::UnsupportedCommand(self.render(layout)))?;
+        bytes.push(tag);
+        util::write_identifier(bytes, mapping)?;
+        key.write(bytes)?;
+        match self {
+            Self::Contains { destination, .. } | Self::Get { destination, .. } => destination.write(bytes),
+            Self::Decrement { value, .. } | Self::Increment { value, .. } | Self::Set { value, .. } => value.write(bytes),
+            Self::GetOrUse { default, destination, .. } => {
+                default.write(bytes)?;
+                destination.write(bytes)
+            },
+            Self::Instruction(_) | Self::Remove { .. } => Ok(()),
+        }
+    }
+
+    // Name of the mapping the command operates on
+    pub fn mapping(&self) -> Option<&str> {
+        match self {
+            Self::Instruction(_) => None,
+            Self::Contains { mapping, .. }
+            | Self::Decrement { mapping, .. }
+            | Self::Get { mapping, .. }
+            | Self::GetOrUse { mapping, .. }
+            | Self::Increment { mapping, .. }
+            | Self::Remove { mapping, .. }
+            | Self::Set { mapping, .. } => Some(mapping),
+        }
+    }
+}
+
+impl Command {
+    // Registers the command writes to
+    pub fn destinations(&self) -> Vec<&Register> {
+        match self {
+            Self::Instruction(i) => i.destinations(),
+            Self::Contains { destination, .. } | Self::Get { destination, .. } | Self::GetOrUse { destination, .. } => vec![destination],
+            Self::Decrement { .. } | Self::Increment { .. } | Self::Remove { .. } | Self::Set { .. } => vec![],
+        }
+    }
+}
+
+impl Command {
+    // Assembly with the keywords of `layout`
+    pub(crate) fn render(&self, layout: CommandLayout) -> String {
+        let keyword = self.keyword(layout).unwrap_or_default();
+        match self {
+            Self::Instruction(i) => i.assembly(),
+            Self::Contains { mapping, key, destination } | Self::Get { mapping, key, destination } => format!("{} {}[{}] into {};", keyword, mapping, key.assembly(), destination.assembly()),
+            Self::Decrement { mapping, key, value } | Self::Increment { mapping, key, value } => format!("{} {}[{}] by {};", keyword, mapping, key.assembly(), value.assembly()),
+            Self::GetOrUse { mapping, key, default, destination } => format!("{} {}[{}] {} into {};", keyword, mapping, key.assembly(), default.assembly(), destination.assembly()),
+            Self::Remove { mapping, key } => format!("remove {}[{}];", mapping, key.assembly()),
+            Self::Set { mapping, key, value } => format!("set {} into {}[{}];", value.assembly(), mapping, key.assembly()),
+        }
+    }
+}
+
+impl Assembly for Command {
+    fn assembly(&self) -> String {
+        self.render(CommandLayout::Current)
+    }
+
+    // A single statement, like `Instruction::leo`
+    fn leo(&self) -> String {
+        printer::statement(&Lower::default().command(self, &RegisterTypes::default()))
+    }
+}
@@ -0,0 +1,151 @@
+use core::fmt::Write;
+
+use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
+use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::inference::{self, Infer};
+use crate::leo;
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
+use crate::util;
+use super::commands::{Command, CommandLayout};
+use super::function::FunctionType;
+use super::registers::{ IoRegister, IOType };
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// On-chain logic of a function, run after the transition with the operands of its `finalize` call
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Finalize {
+    name: String,
+    inputs: Vec<IoRegister>,
+    commands: Vec<Command>,
+    // Numbering of the commands in bytecode, older programs use the legacy one
+    #[cfg_attr(feature = "serde", serde(default))]
+    layout: CommandLayout,
+}
+
+impl Finalize {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inputs(&self) -> &[IoRegister] {
+        &self.inputs
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn layout(&self) -> CommandLayout {
+        self.layout
+    }
+
+    pub fn inputs_mut(&mut self) -> &mut Vec<IoRegister> {
+        &mut self.inputs
+    }
+
+    pub fn commands_mut(&mut self) -> &mut Vec<Command> {
+        &mut self.commands
+    }
+
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        let num_inputs = bytes.read_u16()?;
+        let inputs = (0..num_inputs).map(|_| IoRegister::read(bytes, FunctionType::Finalize, IOType::Input)).collect::<Result<_, _>>()?;
+        let num_commands = bytes.read_u16()?;
+        let commands = (0..num_commands).map(|_| Command::read(bytes)).collect::<Result<_, _>>()?;
+        Ok(Self {
+            name,
+            inputs,
+            commands,
+            layout: bytes.command_layout(),
+        })
+    }
+
+    // `name:` followed by the inputs and commands, after the `finalize` keyword
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect(&TokenKind::Colon)?;
+        let mut inputs = Vec::new();
+        while p.peek_word() == Some("input") {
+            inputs.push(IoRegister::parse(p, FunctionType::Finalize, IOType::Input)?);
+        }
+        let mut commands = Vec::new();
+        let mut layout = None;
+        while Command::is_next(p) {
+            // A block cannot mix the commands of both layouts, there would be no tag for some of them
+            let keyword = p.peek_word().and_then(Command::layout_of);
+            if let (Some(found), Some(expected)) = (keyword, layout) {
+                if found != expected {
+                    return Err(p.error("commands of the current and legacy layouts in the same finalize block"));
+                }
+            }
+            layout = layout.or(keyword);
+            commands.push(Command::parse(p)?);
+        }
+        Ok(Self {
+            name,
+            inputs,
+            commands,
+            layout: layout.unwrap_or_default(),
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        util::write_count(bytes, self.inputs.len(), 2, "finalize inputs")?;
+        self.inputs.iter().try_for_each(|i| i.write(bytes))?;
+        util::write_count(bytes, self.commands.len(), 2, "commands")?;
+        self.commands.iter().try_for_each(|c| c.write(bytes, self.layout))
+    }
+}
+
+impl Finalize {
+    // Signature only, without the commands
+    pub fn interface(&self) -> String {
+        let mut o = String::new();
+        o.write_fmt(format_args!("finalize {}:", self.name)).unwrap();
+        for i in self.inputs.iter() {
+            o.write_fmt(format_args!("\n    {};", i.assembly())).unwrap();
+        }
+        o
+    }
+
+    pub(crate) fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("inputs", Json::Array(self.inputs.iter().map(|i| i.abi()).collect())),
+        ])
+    }
+}
+
+impl Finalize {
+    // Assembly with the inferred type of each register a command writes as a trailing comment
+    pub(crate) fn render(&self, infer: Option<&Infer>) -> String {
+        let types = infer.map(|infer| infer.finalize(self));
+        let mut o = String::new();
+        o.write_fmt(format_args!("finalize {}:", self.name)).unwrap();
+        for i in self.inputs.iter() {
+            o.write_fmt(format_args!("\n    {};", i.assembly())).unwrap();
+        }
+        for c in self.commands.iter() {
+            let comment = types.as_ref().map(|t| inference::annotation(t, &c.destinations())).unwrap_or_default();
+            o.write_fmt(format_args!("\n    {}{}", c.render(self.layout), comment)).unwrap();
+        }
+        o
+    }
+}
+
+impl Assembly for Finalize {
+    fn assembly(&self) -> String {
+        self.render(None)
+    }
+
+    fn leo(&self) -> String {
+        leo::finalize(self)
+    }
+}
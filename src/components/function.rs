@@ -1,103 +1,253 @@
 use core::fmt::Write;
 
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::inference::{self, Infer};
+use crate::leo;
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
 use crate::util;
 use super::registers::{ IoRegister, IOType };
-use super::instructions::Instruction;
+use super::instructions::{ Instruction, Operand, Operands };
+use super::finalize::Finalize;
 use alloc::string::String;
 use alloc::vec::Vec;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FunctionType {
     Function,
     Closure,
     Finalize,
+    #[default]
     Uninitialized
 }
 
-impl Default for FunctionType {
-    fn default() -> Self {
-        FunctionType::Uninitialized
-    }
-}
-
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Function {
     name: String,
     function_type: FunctionType,
-    num_inputs: u16,
     inputs: Vec<IoRegister>,
-    num_instructions: u32,
-    instructions: Vec<Instruction>,
-    num_outputs: u16,
+    pub(crate) instructions: Vec<Instruction>,
     outputs: Vec<IoRegister>,
+    // Operands of the `finalize` call and the finalize block they are passed to
+    finalize: Option<(Operands, Finalize)>,
 }
 
 impl Function {
-    pub fn read(bytes: &mut ByteCode, function_type: FunctionType) -> Self {
-        let name = util::read_identifier(bytes);
-        let num_inputs = bytes.read_u16();
-        let inputs: Vec<IoRegister> = (0..num_inputs).map(|_| IoRegister::read(bytes, function_type, IOType::Input)).collect();
-        let (num_instructions, instructions) = Instruction::read_instructions(bytes);
-        let num_outputs = bytes.read_u16();
-        let outputs: Vec<IoRegister> = (0..num_outputs).map(|_| IoRegister::read(bytes, function_type, IOType::Output)).collect();
-        let _finalize = bytes.read_u8();
-        Self {
+    pub fn read(bytes: &mut ByteCode, function_type: FunctionType) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        bytes.set_context(&name);
+        let num_inputs = bytes.read_u16()?;
+        let inputs: Vec<IoRegister> = (0..num_inputs).map(|_| IoRegister::read(bytes, function_type, IOType::Input)).collect::<Result<_, _>>()?;
+        let instructions = Instruction::read_instructions(bytes)?;
+        let num_outputs = bytes.read_u16()?;
+        let outputs: Vec<IoRegister> = (0..num_outputs).map(|_| IoRegister::read(bytes, function_type, IOType::Output)).collect::<Result<_, _>>()?;
+        let finalize = match function_type {
+            FunctionType::Function => Self::read_finalize(bytes)?,
+            _ => None,
+        };
+        Ok(Self {
+            name,
+            function_type,
+            inputs,
+            instructions,
+            outputs,
+            finalize,
+        })
+    }
+
+    // `name:` followed by the body, after the `function` or `closure` keyword
+    pub(crate) fn parse(p: &mut Parser, function_type: FunctionType) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect(&TokenKind::Colon)?;
+        let mut inputs = Vec::new();
+        while p.peek_word() == Some("input") {
+            inputs.push(IoRegister::parse(p, function_type, IOType::Input)?);
+        }
+        let mut instructions = Vec::new();
+        while Instruction::is_next(p) {
+            instructions.push(Instruction::parse(p)?);
+        }
+        let mut outputs = Vec::new();
+        while p.peek_word() == Some("output") {
+            outputs.push(IoRegister::parse(p, function_type, IOType::Output)?);
+        }
+        // `finalize r0;` as opposed to the `finalize name:` block that follows it
+        let finalize = match function_type {
+            FunctionType::Function if p.peek_word() == Some("finalize") && p.peek_nth(2) != Some(&TokenKind::Colon) => {
+                p.next();
+                let mut operands = Vec::new();
+                while !p.eat(&TokenKind::Semicolon) {
+                    operands.push(Operand::parse(p)?);
+                }
+                p.expect_word("finalize")?;
+                Some((operands, Finalize::parse(p)?))
+            },
+            _ => None,
+        };
+        Ok(Self {
             name,
             function_type,
-            num_inputs,
             inputs,
-            num_instructions,
             instructions,
-            num_outputs,
             outputs,
+            finalize,
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        util::write_count(bytes, self.inputs.len(), 2, "inputs")?;
+        self.inputs.iter().try_for_each(|i| i.write(bytes))?;
+        Instruction::write_instructions(bytes, &self.instructions)?;
+        util::write_count(bytes, self.outputs.len(), 2, "outputs")?;
+        self.outputs.iter().try_for_each(|o| o.write(bytes))?;
+        if let FunctionType::Function = self.function_type {
+            match &self.finalize {
+                None => bytes.push(0),
+                Some((operands, finalize)) => {
+                    bytes.push(1);
+                    util::write_count(bytes, operands.len(), 1, "finalize operands")?;
+                    operands.iter().try_for_each(|o| o.write(bytes))?;
+                    finalize.write(bytes)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    pub fn function_type(&self) -> FunctionType {
+        self.function_type
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inputs(&self) -> &[IoRegister] {
+        &self.inputs
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn outputs(&self) -> &[IoRegister] {
+        &self.outputs
+    }
+
+    pub fn finalize(&self) -> Option<&Finalize> {
+        self.finalize.as_ref().map(|(_, f)| f)
+    }
+
+    pub fn inputs_mut(&mut self) -> &mut Vec<IoRegister> {
+        &mut self.inputs
+    }
+
+    pub fn instructions_mut(&mut self) -> &mut Vec<Instruction> {
+        &mut self.instructions
+    }
+
+    pub fn outputs_mut(&mut self) -> &mut Vec<IoRegister> {
+        &mut self.outputs
+    }
+
+    pub fn finalize_mut(&mut self) -> Option<(&mut Vec<Operand>, &mut Finalize)> {
+        self.finalize.as_mut().map(|(o, f)| (o, f))
+    }
+
+    // Operands passed by the `finalize` statement
+    pub fn finalize_operands(&self) -> Option<&[Operand]> {
+        self.finalize.as_ref().map(|(o, _)| o.as_slice())
+    }
+
+    fn read_finalize(bytes: &mut ByteCode) -> Result<Option<(Operands, Finalize)>, DecodeError> {
+        match bytes.read_u8()? {
+            0 => Ok(None),
+            1 => {
+                let num_operands = bytes.read_u8()?;
+                let operands = (0..num_operands).map(|_| Operand::read(bytes)).collect::<Result<_, _>>()?;
+                Ok(Some((operands, Finalize::read(bytes)?)))
+            },
+            f => Err(DecodeError::InvalidFlag(f, bytes.location_at(bytes.position() - 1))),
         }
     }
 }
 
-impl Assembly for Function {
-    fn assembly(&self) -> String {
+impl Function {
+    fn keyword(&self) -> (&'static str, &'static str) {
+        // (assembly, leo)
+        match self.function_type {
+            FunctionType::Closure => ("closure", "function"),
+            FunctionType::Finalize => ("finalize", "finalize"),
+            _ => ("function", "transition"),
+        }
+    }
+}
+
+impl Function {
+    // Signature only: inputs, outputs and the inputs of the finalize block
+    pub fn interface(&self) -> String {
         let mut o = String::new();
-        o.write_str("function ").unwrap();
-        o.write_fmt(format_args!("{}\n", self.name)).unwrap();
-        for i in self.inputs.clone() {
-            o.write_str("\t").unwrap();
-            o.write_fmt(format_args!("{}\n", i.assembly())).unwrap();
+        o.write_fmt(format_args!("{} {}:", self.keyword().0, self.name)).unwrap();
+        for i in self.inputs.iter().chain(self.outputs.iter()) {
+            o.write_fmt(format_args!("\n    {};", i.assembly())).unwrap();
         }
-        let instructions = self.instructions.iter().map(|i| format!("\t{}", i.assembly())).collect::<Vec<String>>().join("\n"); 
-        o.write_fmt(format_args!("{instructions}\n")).unwrap();
-        for i in self.outputs.clone() {
-            o.write_str("\t").unwrap();
-            o.write_fmt(format_args!("{}\n", i.assembly())).unwrap();
+        if let Some((_, finalize)) = &self.finalize {
+            o.write_fmt(format_args!("\n\n{}", finalize.interface())).unwrap();
         }
         o
     }
 
-    fn leo(&self) -> String {
-        // return the leo source code
-        // function signature followed by code
+    pub(crate) fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("inputs", Json::Array(self.inputs.iter().map(|i| i.abi()).collect())),
+            ("outputs", Json::Array(self.outputs.iter().map(|o| o.abi()).collect())),
+            ("finalize", self.finalize.as_ref().map_or(Json::Null, |(_, f)| f.abi())),
+        ])
+    }
+}
+
+impl Function {
+    // Assembly with the inferred type of each register an instruction writes as a trailing comment
+    pub(crate) fn render(&self, infer: Option<&Infer>) -> String {
+        let types = infer.map(|infer| infer.function(self));
         let mut o = String::new();
-        o.write_str("function ").unwrap();
-        o.write_fmt(format_args!("{}(", self.name)).unwrap();
-        for i in self.inputs.clone() {
-            o.write_fmt(format_args!("{}", i.leo())).unwrap();
-            //comma
-            o.write_str(", ").unwrap();
+        o.write_fmt(format_args!("{} {}:", self.keyword().0, self.name)).unwrap();
+        for i in self.inputs.iter() {
+            o.write_fmt(format_args!("\n    {};", i.assembly())).unwrap();
+        }
+        for i in self.instructions.iter() {
+            let comment = types.as_ref().map(|t| inference::annotation(t, &i.destinations())).unwrap_or_default();
+            o.write_fmt(format_args!("\n    {}{}", i.assembly(), comment)).unwrap();
+        }
+        for i in self.outputs.iter() {
+            o.write_fmt(format_args!("\n    {};", i.assembly())).unwrap();
+        }
+        match &self.finalize {
+            Some((operands, finalize)) if operands.is_empty() => {
+                o.write_fmt(format_args!("\n    finalize;\n\n{}", finalize.render(infer))).unwrap();
+            },
+            Some((operands, finalize)) => {
+                o.write_fmt(format_args!("\n    finalize {};\n\n{}", operands.assembly(), finalize.render(infer))).unwrap();
+            },
+            None => {},
         }
-        //remove last comma
-        o.pop();
-        o.pop();
-        o.write_str(") {\n").unwrap();
-        let instructions = self.instructions.iter().map(|i| format!("\t{}", i.leo())).collect::<Vec<String>>().join("\n"); 
-        o.write_fmt(format_args!("{instructions}\n}}\n")).unwrap();
-
-        match self.outputs.len() {
-            0 => {},
-            1 => { o.write_fmt(format_args!("return {}", self.outputs[0].leo())).unwrap(); },
-            _ => { o.write_fmt(format_args!("return ({})", self.outputs.iter().map(|o| o.leo()).collect::<Vec<String>>().join(", "))).unwrap(); }
-        };
-
         o
     }
-}
\ No newline at end of file
+}
+
+impl Assembly for Function {
+    fn assembly(&self) -> String {
+        self.render(None)
+    }
+
+    fn leo(&self) -> String {
+        leo::function(self)
+    }
+}
@@ -1,13 +1,19 @@
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use crate::components::types;
 use crate::util;
 use super::registers::Register;
 use super::types::{ Type, Literal };
 use crate::output::Assembly;
+use crate::leo::{lower::{self, Lower, Operation}, printer};
+use crate::inference::RegisterTypes;
+use crate::assembler::{self, Parser, TokenKind};
+use crate::error::ParseError;
 use alloc::string::{ String, ToString};
 use alloc::vec::Vec;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Opcode {
     Abs,
     AbsWrapped,
@@ -72,73 +78,91 @@ pub enum Opcode {
 impl Assembly for Opcode {
     fn assembly(&self) -> String {
         match self {
-            Self::Abs => "Abs".to_lowercase(),
-            Self::AbsWrapped => "AbsWrapped".to_lowercase(),
-            Self::Add => "Add".to_lowercase(),
-            Self::AddWrapped => "AddWrapped".to_lowercase(),
-            Self::And => "And".to_lowercase(),
-            Self::AssertEq => "AssertEq".to_lowercase(),
-            Self::AssertNeq => "AssertNeq".to_lowercase(),
-            Self::Call => "Call".to_lowercase(),
-            Self::Cast => "Cast".to_lowercase(),
-            Self::CommitBHP256 => "CommitBHP256".to_lowercase(),
-            Self::CommitBHP512 => "CommitBHP512".to_lowercase(),
-            Self::CommitBHP768 => "CommitBHP768".to_lowercase(),
-            Self::CommitBHP1024 => "CommitBHP1024".to_lowercase(),
-            Self::CommitPED64 => "CommitPED64".to_lowercase(),
-            Self::CommitPED128 => "CommitPED128".to_lowercase(),
-            Self::Div => "Div".to_lowercase(),
-            Self::DivWrapped => "DivWrapped".to_lowercase(),
-            Self::Double => "Double".to_lowercase(),
-            Self::GreaterThan => "GreaterThan".to_lowercase(),
-            Self::GreaterThanOrEqual => "GreaterThanOrEqual".to_lowercase(),
-            Self::HashBHP256 => "HashBHP256".to_lowercase(),
-            Self::HashBHP512 => "HashBHP512".to_lowercase(),
-            Self::HashBHP768 => "HashBHP768".to_lowercase(),
-            Self::HashBHP1024 => "HashBHP1024".to_lowercase(),
-            Self::HashPED64 => "HashPED64".to_lowercase(),
-            Self::HashPED128 => "HashPED128".to_lowercase(),
-            Self::HashPSD2 => "HashPSD2".to_lowercase(),
-            Self::HashPSD4 => "HashPSD4".to_lowercase(),
-            Self::HashPSD8 => "HashPSD8".to_lowercase(),
-            Self::Inv => "Inv".to_lowercase(),
-            Self::IsEq => "IsEq".to_lowercase(),
-            Self::IsNeq => "IsNeq".to_lowercase(),
-            Self::LessThan => "LessThan".to_lowercase(),
-            Self::LessThanOrEqual => "LessThanOrEqual".to_lowercase(),
-            Self::Mod => "Mod".to_lowercase(),
-            Self::Mul => "Mul".to_lowercase(),
-            Self::MulWrapped => "MulWrapped".to_lowercase(),
-            Self::Nand => "Nand".to_lowercase(),
-            Self::Neg => "Neg".to_lowercase(),
-            Self::Nor => "Nor".to_lowercase(),
-            Self::Not => "Not".to_lowercase(),
-            Self::Or => "Or".to_lowercase(),
-            Self::Pow => "Pow".to_lowercase(),
-            Self::PowWrapped => "PowWrapped".to_lowercase(),
-            Self::Rem => "Rem".to_lowercase(),
-            Self::RemWrapped => "RemWrapped".to_lowercase(),
-            Self::Shl => "Shl".to_lowercase(),
-            Self::ShlWrapped => "ShlWrapped".to_lowercase(),
-            Self::Shr => "Shr".to_lowercase(),
-            Self::ShrWrapped => "ShrWrapped".to_lowercase(),
-            Self::Square => "Square".to_lowercase(),
-            Self::SquareRoot => "SquareRoot".to_lowercase(),
-            Self::Sub => "Sub".to_lowercase(),
-            Self::SubWrapped => "SubWrapped".to_lowercase(),
-            Self::Ternary => "Ternary".to_lowercase(),
-            Self::Xor => "Xor".to_lowercase(),
+            Self::Abs => "abs".to_string(),
+            Self::AbsWrapped => "abs.w".to_string(),
+            Self::Add => "add".to_string(),
+            Self::AddWrapped => "add.w".to_string(),
+            Self::And => "and".to_string(),
+            Self::AssertEq => "assert.eq".to_string(),
+            Self::AssertNeq => "assert.neq".to_string(),
+            Self::Call => "call".to_string(),
+            Self::Cast => "cast".to_string(),
+            Self::CommitBHP256 => "commit.bhp256".to_string(),
+            Self::CommitBHP512 => "commit.bhp512".to_string(),
+            Self::CommitBHP768 => "commit.bhp768".to_string(),
+            Self::CommitBHP1024 => "commit.bhp1024".to_string(),
+            Self::CommitPED64 => "commit.ped64".to_string(),
+            Self::CommitPED128 => "commit.ped128".to_string(),
+            Self::Div => "div".to_string(),
+            Self::DivWrapped => "div.w".to_string(),
+            Self::Double => "double".to_string(),
+            Self::GreaterThan => "gt".to_string(),
+            Self::GreaterThanOrEqual => "gte".to_string(),
+            Self::HashBHP256 => "hash.bhp256".to_string(),
+            Self::HashBHP512 => "hash.bhp512".to_string(),
+            Self::HashBHP768 => "hash.bhp768".to_string(),
+            Self::HashBHP1024 => "hash.bhp1024".to_string(),
+            Self::HashPED64 => "hash.ped64".to_string(),
+            Self::HashPED128 => "hash.ped128".to_string(),
+            Self::HashPSD2 => "hash.psd2".to_string(),
+            Self::HashPSD4 => "hash.psd4".to_string(),
+            Self::HashPSD8 => "hash.psd8".to_string(),
+            Self::Inv => "inv".to_string(),
+            Self::IsEq => "is.eq".to_string(),
+            Self::IsNeq => "is.neq".to_string(),
+            Self::LessThan => "lt".to_string(),
+            Self::LessThanOrEqual => "lte".to_string(),
+            Self::Mod => "mod".to_string(),
+            Self::Mul => "mul".to_string(),
+            Self::MulWrapped => "mul.w".to_string(),
+            Self::Nand => "nand".to_string(),
+            Self::Neg => "neg".to_string(),
+            Self::Nor => "nor".to_string(),
+            Self::Not => "not".to_string(),
+            Self::Or => "or".to_string(),
+            Self::Pow => "pow".to_string(),
+            Self::PowWrapped => "pow.w".to_string(),
+            Self::Rem => "rem".to_string(),
+            Self::RemWrapped => "rem.w".to_string(),
+            Self::Shl => "shl".to_string(),
+            Self::ShlWrapped => "shl.w".to_string(),
+            Self::Shr => "shr".to_string(),
+            Self::ShrWrapped => "shr.w".to_string(),
+            Self::Square => "square".to_string(),
+            Self::SquareRoot => "sqrt".to_string(),
+            Self::Sub => "sub".to_string(),
+            Self::SubWrapped => "sub.w".to_string(),
+            Self::Ternary => "ternary".to_string(),
+            Self::Xor => "xor".to_string(),
         }   
     }
 
+    // Operator, method or built-in the instruction is written with in Leo, e.g. `+`, `add_wrapped`
+    // or `BHP256::hash`. Calls are written as their callee and casts as the type they build, so
+    // neither has one and both give an empty string.
     fn leo(&self) -> String {
-        unimplemented!()
+        match lower::operation(*self) {
+            Operation::Binary(op) => printer::binary_operator(op).to_string(),
+            Operation::Unary(op) => printer::unary_operator(op).to_string(),
+            Operation::Method(name) | Operation::Function(name) => name.to_string(),
+            Operation::Ternary => "?:".to_string(),
+            Operation::Call | Operation::Cast => String::new(),
+        }
+    }
+}
+
+impl Opcode {
+    // Opcode of an assembly mnemonic such as `add.w`
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        (0..=55).filter_map(|i| Self::try_from(i).ok()).find(|o| o.assembly() == mnemonic)
     }
 }
 
-impl From<u16> for Opcode {
-    fn from(value: u16) -> Self {
-        match value {
+impl TryFrom<u16> for Opcode {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
             0 => Self::Abs,
             1 => Self::AbsWrapped,
             2 => Self::Add,
@@ -195,8 +219,8 @@ impl From<u16> for Opcode {
             53 => Self::SubWrapped,
             54 => Self::Ternary,
             55 => Self::Xor,
-            _ => unreachable!(),
-        }
+            _ => return Err(value),
+        })
     }
 }
 
@@ -258,19 +282,41 @@ const BINARY: &[Opcode] = &[
 ];
 
 const ASSERT: &[Opcode] = &[Opcode::AssertEq, Opcode::AssertNeq];
-const IS_CHECK: &[Opcode] = &[Opcode::IsEq, Opcode::IsNeq];
 
-#[derive(Debug)]
+// Number of operands of the instructions with a fixed count, everything but calls and casts
+fn arity(opcode: Opcode) -> usize {
+    match opcode {
+        Opcode::Ternary => 3,
+        o if UNARY.contains(&o) => 1,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Locator {
     Internal(String),
-    External((String, String, String))
+    External((String, String, String)),
+    // A program id on its own, the value of program id operands such as `credits.aleo`
+    Program((String, String)),
+}
+
+impl Locator {
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Self::Internal(name) => util::write_identifier(bytes, name),
+            Self::External((p, n, r)) => util::write_locator(bytes, (p, n, r)),
+            Self::Program((p, n)) => util::write_programid(bytes, (p, n)),
+        }
+    }
 }
 
 impl Assembly for Locator {
     fn assembly(&self) -> String {
         match self {
             Self::Internal(s) => s.clone(),
-            Self::External((a, b, c)) => format!("{}.{}.{}", a, b, c),
+            Self::External((a, b, c)) => format!("{}.{}/{}", a, b, c),
+            Self::Program((a, b)) => format!("{}.{}", a, b),
         }
     }
 
@@ -279,7 +325,8 @@ impl Assembly for Locator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operand {
     Literal(Literal),
     Register(Register),
@@ -288,13 +335,48 @@ pub enum Operand {
 }
 
 impl Operand {
-    fn read(bytes: &mut ByteCode) -> Self {
-        match bytes.read_u8() {
-            0 => Self::Literal(Literal::read(bytes)),
-            1 => Self::Register(Register::read(bytes)),
-            2 => Self::ProgramId(Locator::External(util::read_locator(bytes))),
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        Ok(match bytes.read_u8()? {
+            0 => Self::Literal(Literal::read(bytes)?),
+            1 => Self::Register(Register::read(bytes)?),
+            2 => Self::ProgramId(Locator::Program(util::read_programid(bytes)?)),
             3 => Self::Caller,
-            _ => unreachable!(),    
+            t => return Err(DecodeError::UnknownOperandTag(t, bytes.location_at(bytes.position() - 1))),
+        })
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        match p.peek_word() {
+            Some("self.caller") => {
+                p.next();
+                Ok(Self::Caller)
+            },
+            Some(w) if Register::from_word(w).is_some() => Ok(Self::Register(Register::parse(p)?)),
+            Some(w) if assembler::split_programid(w).is_some() => Ok(Self::ProgramId(Locator::Program(p.programid()?))),
+            _ => Ok(Self::Literal(Literal::parse(p)?)),
+        }
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Self::Literal(literal) => {
+                bytes.push(0);
+                literal.write(bytes)
+            },
+            Self::Register(register) => {
+                bytes.push(1);
+                register.write(bytes)
+            },
+            Self::ProgramId(locator @ Locator::Program(_)) => {
+                bytes.push(2);
+                locator.write(bytes)
+            },
+            // Callees are written by the call instruction itself
+            Self::ProgramId(locator) => Err(EncodeError::InvalidProgramId(locator.assembly())),
+            Self::Caller => {
+                bytes.push(3);
+                Ok(())
+            },
         }
     }
 }
@@ -305,7 +387,7 @@ impl Assembly for Operand {
             Self::Register(reg) => reg.assembly(),
             Self::ProgramId(loc) => loc.assembly(),
             Self::Literal(lit) => lit.assembly(),
-            Self::Caller => "caller".to_string(),
+            Self::Caller => "self.caller".to_string(),
         }
     }
 
@@ -314,7 +396,7 @@ impl Assembly for Operand {
     }
 }
 
-type Operands = Vec<Operand>;
+pub type Operands = Vec<Operand>;
 
 impl Assembly for Operands {
     fn assembly(&self) -> String {
@@ -324,12 +406,17 @@ impl Assembly for Operands {
             .join(" ")
     }
 
+    // Argument list, e.g. `r0, 1u64`
     fn leo(&self) -> String {
-        unimplemented!()
+        self.iter()
+            .map(|o| o.leo())
+            .collect::<Vec<String>>()
+            .join(", ")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Output {
     Single(Register),
     Multiple(Vec<Register>),
@@ -350,14 +437,16 @@ impl Assembly for Output {
     fn leo(&self) -> String {
         match self {
             Self::Single(reg) => reg.assembly(),
-            Self::Multiple(regs) => format!("({})", regs.iter().map(|r| r.assembly()).collect::<Vec<String>>().join(",")),
+            Self::Multiple(regs) if regs.len() == 1 => regs[0].leo(),
+            Self::Multiple(regs) => format!("({})", regs.iter().map(|r| r.leo()).collect::<Vec<String>>().join(", ")),
             Self::Cast((r, t)) => format!("{} as {}", r.assembly(), t.assembly()),
             Self::None => "".to_string(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction {
     opcode: Opcode,
     operands: Operands,
@@ -365,87 +454,128 @@ pub struct Instruction {
 }
 
 impl Instruction {
-    fn read_operands(bytes: &mut ByteCode, n: u8) -> Operands {
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn operands(&self) -> &[Operand] {
+        &self.operands
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    // Registers the instruction writes to
+    pub fn destinations(&self) -> Vec<&Register> {
+        match &self.output {
+            Output::Single(r) | Output::Cast((r, _)) => vec![r],
+            Output::Multiple(regs) => regs.iter().collect(),
+            Output::None => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> &mut Vec<Operand> {
+        &mut self.operands
+    }
+
+    pub fn destinations_mut(&mut self) -> Vec<&mut Register> {
+        match &mut self.output {
+            Output::Single(r) | Output::Cast((r, _)) => vec![r],
+            Output::Multiple(regs) => regs.iter_mut().collect(),
+            Output::None => vec![],
+        }
+    }
+
+    // Function or closure invoked by a `call` instruction
+    pub fn callee(&self) -> Option<&Locator> {
+        match (self.opcode, self.operands.first()) {
+            (Opcode::Call, Some(Operand::ProgramId(locator))) => Some(locator),
+            _ => None,
+        }
+    }
+
+    fn read_operands(bytes: &mut ByteCode, n: u8) -> Result<Operands, DecodeError> {
         (0..n).map(|_| Operand::read(bytes)).collect()
     }
 
-    fn read_cast_instruction(bytes: &mut ByteCode) -> Self {
-        let num_inputs = bytes.read_u8();
-        assert!(num_inputs <= 8 && num_inputs != 0, "number  of cast arguments must be between 1 and 8");
-        let operands: Vec<Operand> = (0..num_inputs).map(|_| Operand::read(bytes)).collect();
-        assert_eq!(bytes.read_u8(), 0);
-        let output = Register{locator: bytes.read_u8() as usize, identifiers: vec![]};
-        let value_type  = match bytes.peek() {
-            0 => { _ = bytes.read_u8();  types::read_plaintext_type(bytes)},
-            1 => types::read_plaintext_type(bytes),
-            _ => unreachable!(),
-        };
-        Self {
+    fn read_cast_instruction(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let num_inputs = bytes.read_u8()?;
+        if num_inputs > 8 || num_inputs == 0 {
+            // number of cast arguments must be between 1 and 8
+            return Err(DecodeError::InvalidOperandCount(num_inputs, bytes.location_at(bytes.position() - 1)));
+        }
+        let operands = Self::read_operands(bytes, num_inputs)?;
+        let output = Register::read(bytes)?;
+        let value_type = types::read_register_type(bytes)?;
+        Ok(Self {
             opcode: Opcode::Cast,
-            operands: operands,
+            operands,
             output: Output::Cast((output, value_type)),
-        }
+        })
     }
 
-    fn read_call_instruction(bytes: &mut ByteCode) -> Self {
-        let callee = match bytes.read_u8() {
-            1 => Locator::Internal(util::read_identifier(bytes)),
-            _ => Locator::External(util::read_locator(bytes)),
+    fn read_call_instruction(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let callee = match bytes.read_u8()? {
+            0 => Locator::External(util::read_locator(bytes)?),
+            1 => Locator::Internal(util::read_identifier(bytes)?),
+            v => return Err(DecodeError::UnknownCallVariant(v, bytes.location_at(bytes.position() - 1))),
         };
 
-        let num_inputs = bytes.read_u8();
+        let num_inputs = bytes.read_u8()?;
         let mut operands = vec![Operand::ProgramId(callee)];
-        operands.append(Self::read_operands(bytes, num_inputs).as_mut());
-        let num_outputs = bytes.read_u8();
-        let output = Output::Multiple((0..num_outputs).map(|_| Register::read(bytes)).collect());
+        operands.append(Self::read_operands(bytes, num_inputs)?.as_mut());
+        let num_outputs = bytes.read_u8()?;
+        let output = Output::Multiple((0..num_outputs).map(|_| Register::read(bytes)).collect::<Result<_, _>>()?);
         
-        Self {
+        Ok(Self {
             opcode: Opcode::Call,
-            operands: operands,
+            operands,
             output,
-        }
+        })
     }
 
-    fn read_assert_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Self {
-        Self {
+    fn read_assert_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Result<Self, DecodeError> {
+        Ok(Self {
             opcode,
-            operands: Self::read_operands(bytes, 2),
+            operands: Self::read_operands(bytes, 2)?,
             output: Output::None,
-        }
+        })
     }
 
-    fn read_ternary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Self {
-        Self {
+    fn read_ternary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Result<Self, DecodeError> {
+        Ok(Self {
             opcode,
-            operands: Self::read_operands(bytes, 3),
-            output: Output::Single(Register::read(bytes)),
-        }
+            operands: Self::read_operands(bytes, 3)?,
+            output: Output::Single(Register::read(bytes)?),
+        })
     }
 
-    fn read_unary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Self {
-        Self {
+    fn read_unary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Result<Self, DecodeError> {
+        Ok(Self {
             opcode,
-            operands: Self::read_operands(bytes, 1),
-            output: Output::Single(Register::read(bytes)),
-        }
+            operands: Self::read_operands(bytes, 1)?,
+            output: Output::Single(Register::read(bytes)?),
+        })
     }
 
-    fn read_binary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Self {
-        Self {
+    fn read_binary_instruction(bytes: &mut ByteCode, opcode: Opcode) -> Result<Self, DecodeError> {
+        Ok(Self {
             opcode,
-            operands: Self::read_operands(bytes, 2),
-            output: Output::Single(Register::read(bytes)),
-        }
+            operands: Self::read_operands(bytes, 2)?,
+            output: Output::Single(Register::read(bytes)?),
+        })
     }
 
-    pub fn read_instructions(bytes: &mut ByteCode) -> (u32, Vec<Self>) {
-        let num = bytes.read_u32();
-        let instructions = (0..num).map(|_| Self::read(bytes)).collect();
-        (num, instructions)
+    pub fn read_instructions(bytes: &mut ByteCode) -> Result<Vec<Self>, DecodeError> {
+        let num = bytes.read_u32()?;
+        (0..num).map(|_| Self::read(bytes)).collect()
     }
 
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        let opcode = Opcode::from(bytes.read_u16());
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let start = bytes.position();
+        let opcode = bytes.read_u16()?;
+        let opcode = Opcode::try_from(opcode).map_err(|o| DecodeError::UnknownOpcode(o, bytes.location_at(start)))?;
         match opcode {
             Opcode::Call => Self::read_call_instruction(bytes),
             Opcode::Ternary => Self::read_ternary_instruction(bytes, opcode),
@@ -453,88 +583,156 @@ impl Instruction {
             o if ASSERT.contains(&o) => Self::read_assert_instruction(bytes, opcode),
             o if UNARY.contains(&o) => Self::read_unary_instruction(bytes, opcode),
             o if BINARY.contains(&o) => Self::read_binary_instruction(bytes, opcode),
-            _ => unreachable!(),
+            o => Err(DecodeError::UnknownOpcode(o as u16, bytes.location_at(start))),
         }
     }
-}
 
-impl Assembly for Instruction {
-    fn assembly(&self) -> String {
-        match self.opcode {
-            Opcode::Cast => {
-                format!("{} {} into {}", self.opcode.assembly(), self.operands.assembly(), self.output.assembly())
+    // Whether the next statement is an instruction
+    pub(crate) fn is_next(p: &Parser) -> bool {
+        p.peek_word().and_then(Opcode::from_mnemonic).is_some()
+    }
+
+    // Operands up to `into` or the end of the statement
+    fn parse_operands(p: &mut Parser) -> Result<Operands, ParseError> {
+        let mut operands = Vec::new();
+        while !matches!(p.peek(), None | Some(TokenKind::Semicolon)) && p.peek_word() != Some("into") {
+            operands.push(Operand::parse(p)?);
+        }
+        Ok(operands)
+    }
+
+    fn parse_cast_instruction(p: &mut Parser) -> Result<Self, ParseError> {
+        let start = p.position();
+        let operands = Self::parse_operands(p)?;
+        if operands.is_empty() || operands.len() > 8 {
+            return Err(p.error_at(start, "`cast` takes between 1 and 8 operands"));
+        }
+        p.expect_word("into")?;
+        let output = Register::parse(p)?;
+        p.expect_word("as")?;
+        let value_type = types::parse_register_type(p)?;
+        Ok(Self {
+            opcode: Opcode::Cast,
+            operands,
+            output: Output::Cast((output, value_type)),
+        })
+    }
+
+    fn parse_call_instruction(p: &mut Parser) -> Result<Self, ParseError> {
+        let start = p.position();
+        let target = p.next_word()?;
+        let callee = match assembler::split_locator(&target) {
+            Some(locator) => Locator::External(locator),
+            None if assembler::is_identifier(&target) => Locator::Internal(target),
+            None => return Err(p.error_at(start, &format!("invalid call target `{}`", target))),
+        };
+        let mut operands = vec![Operand::ProgramId(callee)];
+        operands.append(&mut Self::parse_operands(p)?);
+        let mut outputs = Vec::new();
+        if p.eat_word("into") {
+            while !matches!(p.peek(), None | Some(TokenKind::Semicolon)) {
+                outputs.push(Register::parse(p)?);
+            }
+        }
+        Ok(Self {
+            opcode: Opcode::Call,
+            operands,
+            output: Output::Multiple(outputs),
+        })
+    }
+
+    // `add r0 r1 into r2;`
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let start = p.position();
+        let mnemonic = p.next_word()?;
+        let opcode = Opcode::from_mnemonic(&mnemonic).ok_or_else(|| p.error_at(start, &format!("unknown instruction `{}`", mnemonic)))?;
+        let instruction = match opcode {
+            Opcode::Call => Self::parse_call_instruction(p)?,
+            Opcode::Cast => Self::parse_cast_instruction(p)?,
+            _ => {
+                let operands = Self::parse_operands(p)?;
+                let expected = arity(opcode);
+                if operands.len() != expected {
+                    return Err(p.error_at(start, &format!("`{}` takes {} operands", mnemonic, expected)));
+                }
+                let output = match ASSERT.contains(&opcode) {
+                    true => Output::None,
+                    false => {
+                        p.expect_word("into")?;
+                        Output::Single(Register::parse(p)?)
+                    },
+                };
+                Self { opcode, operands, output }
             },
-            _ => format!("{} {} into {}", self.opcode.assembly(), self.operands.assembly(), self.output.assembly()),
-        }    
+        };
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(instruction)
     }
 
-    fn leo(&self) -> String {
-        match self.opcode {
-            Opcode::Abs => format!("{} = {}.abs()", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::AbsWrapped => format!("{}.abs_wrapped()", self.operands[0].assembly()),
-            Opcode::Add => format!("{} = {} + {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::AddWrapped => format!("{} = {}.add_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::And => format!("{} = {} & {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::AssertEq => format!("assert_eq({}, {})", self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::AssertNeq => format!("assert_eq({}, {})", self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Call => format!("({}) = {}({})", self.output.leo(), self.operands[0].assembly(), self.operands[1..].iter().map(|o| o.leo()).collect::<Vec<String>>().join(", ")),
-            Opcode::Cast => {
-                let (t, r) = {
-                    if let Output::Cast((t, r)) = &self.output {
-                        (t, r)
-                    } else {
-                        unreachable!()
-                    }
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        let invalid = || EncodeError::InvalidInstruction(self.assembly());
+        bytes.extend_from_slice(&(self.opcode as u16).to_le_bytes());
+        match (self.opcode, &self.output) {
+            (Opcode::Call, Output::Multiple(outputs)) => {
+                match self.operands.first() {
+                    Some(Operand::ProgramId(Locator::Internal(name))) => {
+                        bytes.push(1);
+                        util::write_identifier(bytes, name)?;
+                    },
+                    Some(Operand::ProgramId(locator @ Locator::External(_))) => {
+                        bytes.push(0);
+                        locator.write(bytes)?;
+                    },
+                    // The first operand of a call is its callee
+                    _ => return Err(invalid()),
+                }
+                util::write_count(bytes, self.operands.len() - 1, 1, "call inputs")?;
+                self.operands[1..].iter().try_for_each(|o| o.write(bytes))?;
+                util::write_count(bytes, outputs.len(), 1, "call outputs")?;
+                outputs.iter().try_for_each(|r| r.write(bytes))
+            },
+            (Opcode::Cast, Output::Cast((register, value_type))) if (1..=8).contains(&self.operands.len()) => {
+                bytes.push(self.operands.len() as u8);
+                self.operands.iter().try_for_each(|o| o.write(bytes))?;
+                register.write(bytes)?;
+                types::write_register_type(bytes, value_type)
+            },
+            (Opcode::Call | Opcode::Cast, _) => Err(invalid()),
+            (opcode, output) => {
+                let shape = match (ASSERT.contains(&opcode), output) {
+                    (true, Output::None) | (false, Output::Single(_)) => self.operands.len() == arity(opcode),
+                    _ => false,
                 };
-                format!("{} = {}{{ {} }}", r.assembly(), t.assembly(), self.operands.iter().map(|o| o.leo()).collect::<Vec<String>>().join(", "))
+                if !shape {
+                    return Err(invalid());
+                }
+                self.operands.iter().try_for_each(|o| o.write(bytes))?;
+                match output {
+                    Output::Single(register) => register.write(bytes),
+                    _ => Ok(()),
+                }
             },
-            Opcode::CommitBHP256 => format!("{} = BHP256::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::CommitBHP512 => format!("{} = BHP512::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::CommitBHP768 => format!("{} = BHP768::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::CommitBHP1024 => format!("{} = BHP1024::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::CommitPED64 => format!("{} = PED64::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::CommitPED128 => format!("{} = PED128::commit({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::Div => format!("{} = {} / {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::DivWrapped => format!("{} = {}.div_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Double => format!("{} = {}.double()", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::GreaterThan => format!("{} = {} > {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::GreaterThanOrEqual => format!("{} = {} >= {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::HashBHP256 => format!("{} = BHP256::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashBHP512 => format!("{} = BHP512::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashBHP768 => format!("{} = BHP768::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashBHP1024 => format!("{} = BHP1024::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashPED64 => format!("{} = PED64::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashPED128 => format!("{} = PED128::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashPSD2 => format!("{} = PSD2::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashPSD4 => format!("{} = PSD4::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::HashPSD8 => format!("{} = PSD8::hash({})", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::Inv => format!("{} = {}.inv()", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::IsEq => format!("{} = {} == {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::IsNeq => format!("{} = {} != {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::LessThan => format!("{} = {} < {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::LessThanOrEqual => format!("{} = {} <= {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Mod => format!("{} = {}.mod({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Mul => format!("{} = {}*{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::MulWrapped => format!("{} = {}*{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Nand => format!("{} = {}.nand({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Neg => format!("{} = -{}", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::Nor => format!("{} = {}.nor({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Not => format!("{} = ~{}", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::Or => format!("{} = {} || {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Pow => format!("{} = {}**{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::PowWrapped => format!("{} = {}.pow_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Rem => format!("{} = {}%{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::RemWrapped => format!("{} = {}.rem_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Shl => format!("{} = {} << {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::ShlWrapped => format!("{} = {}.shl_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Shr => format!("{} = {}>>{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::ShrWrapped => format!("{} = {}.shr_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Square => format!("{} = {}.square()", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::SquareRoot => format!("{} = {}.square_root()", self.output.assembly(), self.operands[0].assembly()),
-            Opcode::Sub => format!("{} = {}-{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::SubWrapped => format!("{} = {}.sub_wrapped({})", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
-            Opcode::Ternary => format!("{} = {} ? {} : {}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly(), self.operands[2].assembly()),
-            Opcode::Xor => format!("{} = {}^{}", self.output.leo(), self.operands[0].assembly(), self.operands[1].assembly()),
         }
     }
+
+    pub fn write_instructions(bytes: &mut Vec<u8>, instructions: &[Self]) -> Result<(), EncodeError> {
+        util::write_count(bytes, instructions.len(), 4, "instructions")?;
+        instructions.iter().try_for_each(|i| i.write(bytes))
+    }
+}
+
+impl Assembly for Instruction {
+    fn assembly(&self) -> String {
+        match &self.output {
+            Output::None => format!("{} {};", self.opcode.assembly(), self.operands.assembly()),
+            Output::Multiple(regs) if regs.is_empty() => format!("{} {};", self.opcode.assembly(), self.operands.assembly()),
+            output => format!("{} {} into {};", self.opcode.assembly(), self.operands.assembly(), output.assembly()),
+        }
+    }
+
+    // A single statement, e.g. `r2 = r0 + r1`: only `decompile` knows the types to declare and
+    // folds single-use registers into the statements using them
+    fn leo(&self) -> String {
+        printer::statement(&Lower::default().instruction(self, &RegisterTypes::default()))
+    }
 }
@@ -1,31 +1,85 @@
 use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::leo::{ast::Item, lower::Lower, printer};
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use super::{ Type, Attribute, types };
 use crate::util;
 use alloc::string::String;
+use alloc::vec::Vec;
 
-#[derive(Debug)]
-struct KeyValue {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyValue {
     name: String,
     attribute_type: Attribute,
     value_type: Type,
 }
 
 impl KeyValue {
-    fn read(bytes: &mut ByteCode) -> Self {
-        let name = util::read_identifier(bytes);
-        let attribute_type = match bytes.read_u8() {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn attribute(&self) -> &Attribute {
+        &self.attribute_type
+    }
+
+    pub fn value_type(&self) -> &Type {
+        &self.value_type
+    }
+
+    fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        let attribute_type = match bytes.read_u8()? {
             0 => Attribute::Public,
             1 => Attribute::Record,
             2 => Attribute::ExternalRecord,
-            _ => unreachable!(),
+            a => return Err(DecodeError::UnknownAttribute(a, bytes.location_at(bytes.position() - 1))),
         };
-        let value_type = types::read_plaintext_type(bytes);
-        Self {
+        let value_type = types::read_plaintext_type(bytes)?;
+        Ok(Self {
             name,
             attribute_type,
             value_type
-        }
+        })
+    }
+
+    // `owner as address.public;`
+    fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect_word("as")?;
+        let start = p.position();
+        let (value_type, attribute_type) = match types::parse_plaintext_attribute_type(p)? {
+            (value_type, Attribute::Public) => (value_type, Attribute::Public),
+            _ => return Err(p.error_at(start, "mapping keys and values must be public")),
+        };
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(Self {
+            name,
+            attribute_type,
+            value_type
+        })
+    }
+
+    fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("type", self.value_type.assembly().into()),
+            ("visibility", self.attribute_type.assembly().into()),
+        ])
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        bytes.push(match self.attribute_type {
+            Attribute::Record => 1,
+            Attribute::ExternalRecord => 2,
+            _ => 0,
+        });
+        types::write_plaintext_type(bytes, &self.value_type)
     }
 }
 
@@ -35,11 +89,12 @@ impl Assembly for KeyValue {
     }
 
     fn leo(&self) -> String {
-        return String::new();
+        String::new()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapping {
     name: String,
     key: KeyValue,
@@ -47,21 +102,60 @@ pub struct Mapping {
 }
 
 impl Mapping {
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        Self {
-            name: util::read_identifier(bytes),
-            key: KeyValue::read(bytes),
-            value: KeyValue::read(bytes),
-        }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn key(&self) -> &KeyValue {
+        &self.key
+    }
+
+    pub fn value(&self) -> &KeyValue {
+        &self.value
+    }
+
+    pub(crate) fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("key", self.key.abi()),
+            ("value", self.value.abi()),
+        ])
+    }
+
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        bytes.set_context(&name);
+        Ok(Self {
+            name,
+            key: KeyValue::read(bytes)?,
+            value: KeyValue::read(bytes)?,
+        })
+    }
+
+    // `name:` followed by the key and value, after the `mapping` keyword
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect(&TokenKind::Colon)?;
+        p.expect_word("key")?;
+        let key = KeyValue::parse(p)?;
+        p.expect_word("value")?;
+        let value = KeyValue::parse(p)?;
+        Ok(Self { name, key, value })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        self.key.write(bytes)?;
+        self.value.write(bytes)
     }
 }
 
 impl Assembly for Mapping {
     fn assembly(&self) -> String {
-        format!("mapping {}\n\tkey {}\n\tvalue {}\n", self.name, self.key.assembly(), self.value.assembly())
+        format!("mapping {}:\n    key {};\n    value {};", self.name, self.key.assembly(), self.value.assembly())
     }
 
     fn leo(&self) -> String {
-        format!("mapping {}: {} => {};\n", self.name, self.key.value_type.assembly(), self.value.value_type.assembly())
+        printer::item(&Item::Mapping(Lower::default().mapping(self)))
     }
 }
\ No newline at end of file
@@ -5,9 +5,96 @@ pub mod instructions;
 pub mod mapping;
 pub mod structs;
 pub mod records;
+pub mod commands;
+pub mod finalize;
 
 use types::{Type, Attribute};
 pub use mapping::Mapping;
 pub use function::Function;
 pub use structs::Struct;
 pub use records::Record;
+
+use crate::output::Assembly;
+use crate::error::EncodeError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Top level declaration of a program, in the order it appears in the bytecode
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Component {
+    Mapping(Mapping),
+    Struct(Struct),
+    Record(Record),
+    Function(Function),
+}
+
+impl Component {
+    // Tag followed by the component itself, as laid out in the program
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Self::Mapping(m) => {
+                bytes.push(0);
+                m.write(bytes)
+            },
+            Self::Struct(s) => {
+                bytes.push(1);
+                s.write(bytes)
+            },
+            Self::Record(r) => {
+                bytes.push(2);
+                r.write(bytes)
+            },
+            Self::Function(f) => {
+                bytes.push(match f.function_type() {
+                    function::FunctionType::Closure => 3,
+                    function::FunctionType::Function => 4,
+                    _ => return Err(EncodeError::InvalidFunctionType(String::from(f.name()))),
+                });
+                f.write(bytes)
+            },
+        }
+    }
+
+    // Keyword the component is declared with
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Mapping(_) => "mapping",
+            Self::Struct(_) => "struct",
+            Self::Record(_) => "record",
+            Self::Function(f) => match f.function_type() {
+                function::FunctionType::Closure => "closure",
+                _ => "function",
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Mapping(m) => m.name(),
+            Self::Struct(s) => s.name(),
+            Self::Record(r) => r.name(),
+            Self::Function(f) => f.name(),
+        }
+    }
+}
+
+impl Assembly for Component {
+    fn assembly(&self) -> String {
+        match self {
+            Self::Mapping(m) => m.assembly(),
+            Self::Struct(s) => s.assembly(),
+            Self::Record(r) => r.assembly(),
+            Self::Function(f) => f.assembly(),
+        }
+    }
+
+    fn leo(&self) -> String {
+        match self {
+            Self::Mapping(m) => m.leo(),
+            Self::Struct(s) => s.leo(),
+            Self::Record(r) => r.leo(),
+            Self::Function(f) => f.leo(),
+        }
+    }
+}
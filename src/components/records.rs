@@ -1,24 +1,69 @@
 use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::leo::{ast::Item, lower::Lower, printer};
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
 use crate::{ByteCode, util};
+use crate::error::{DecodeError, EncodeError};
 
 use super::types::{Type, Attribute, self};
 use alloc::string::String;
 use alloc::vec::Vec;
 
-#[derive(Debug)]
-struct Entry {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
     name: String,
     value_type: Type,
     attribute_type: Attribute,
 }
 
 impl Entry {
-    fn read(bytes: &mut ByteCode) -> Self {
-        Self {
-            name: util::read_identifier(bytes),
-            attribute_type: types::read_attribute(bytes),
-            value_type: types::read_plaintext_type(bytes),
-        }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value_type(&self) -> &Type {
+        &self.value_type
+    }
+
+    pub fn attribute(&self) -> &Attribute {
+        &self.attribute_type
+    }
+
+    fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        Ok(Self {
+            name: util::read_identifier(bytes)?,
+            attribute_type: types::read_attribute(bytes)?,
+            value_type: types::read_plaintext_type(bytes)?,
+        })
+    }
+
+    // `amount as u64.private;`
+    fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect_word("as")?;
+        let (value_type, attribute_type) = types::parse_plaintext_attribute_type(p)?;
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(Self {
+            name,
+            value_type,
+            attribute_type,
+        })
+    }
+
+    fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("type", self.value_type.assembly().into()),
+            ("visibility", self.attribute_type.assembly().into()),
+        ])
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        types::write_attribute(bytes, &self.attribute_type);
+        types::write_plaintext_type(bytes, &self.value_type)
     }
 }
 
@@ -32,7 +77,8 @@ impl Assembly for Entry {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Record {
     name: String,
     owner_attribute: Attribute,
@@ -41,41 +87,137 @@ pub struct Record {
 }
 
 impl Record {
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        let name = util::read_identifier(bytes);
-        
-        let owner_attribute = match bytes.read_u8() {
-            0 => Attribute::Public,
-            1 => Attribute::Private,
-            _ => unreachable!(),
-        };
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Visibility of the `owner` address
+    pub fn owner(&self) -> &Attribute {
+        &self.owner_attribute
+    }
+
+    // Visibility of the `gates` amount
+    pub fn gates(&self) -> &Attribute {
+        &self.gates_attribute
+    }
+
+    // Entries after `owner` and `gates`
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub(crate) fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("owner", self.owner_attribute.assembly().into()),
+            ("gates", self.gates_attribute.assembly().into()),
+            ("entries", Json::Array(self.entries.iter().map(|e| e.abi()).collect())),
+        ])
+    }
+
+    fn read_visibility(bytes: &mut ByteCode) -> Result<Attribute, DecodeError> {
+        match bytes.read_u8()? {
+            0 => Ok(Attribute::Public),
+            1 => Ok(Attribute::Private),
+            a => Err(DecodeError::UnknownAttribute(a, bytes.location_at(bytes.position() - 1))),
+        }
+    }
+
+    // `owner` and `gates` can only be public or private
+    fn write_visibility(bytes: &mut Vec<u8>, attribute: &Attribute, value_type: &str) -> Result<(), EncodeError> {
+        bytes.push(match attribute {
+            Attribute::Public => 0,
+            Attribute::Private => 1,
+            _ => return Err(EncodeError::MismatchedAttribute(String::from(value_type), attribute.assembly())),
+        });
+        Ok(())
+    }
 
-        let gates_attribute = match bytes.read_u8() {
-            0 => Attribute::Public,
-            1 => Attribute::Private,
-            _ => unreachable!(),
+    // `owner` and `gates` come first with their fixed types and only their visibility varies
+    fn parse_visibility(p: &mut Parser, name: &str, value_type: &str) -> Result<Attribute, ParseError> {
+        p.expect_word(name)?;
+        p.expect_word("as")?;
+        let start = p.position();
+        let word = p.next_word()?;
+        let attribute = match word.strip_prefix(value_type).and_then(|v| v.strip_prefix('.')) {
+            Some("public") => Attribute::Public,
+            Some("private") => Attribute::Private,
+            _ => return Err(p.error_at(start, &format!("expected `{}` as `{}.public` or `{}.private`", name, value_type, value_type))),
         };
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(attribute)
+    }
 
-        let num_entries = bytes.read_u16();
-        let entries = (0..num_entries).map(|_| Entry::read(bytes)).collect();
+    // `name:` followed by the entries, after the `record` keyword
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect(&TokenKind::Colon)?;
+        let owner_attribute = Self::parse_visibility(p, "owner", "address")?;
+        let gates_attribute = Self::parse_visibility(p, "gates", "u64")?;
+        let mut entries = Vec::new();
+        while p.peek_nth(1) == Some(&TokenKind::Word(String::from("as"))) {
+            entries.push(Entry::parse(p)?);
+        }
+        Ok(Self {
+            name,
+            owner_attribute,
+            gates_attribute,
+            entries
+        })
+    }
+
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        bytes.set_context(&name);
+
+        let owner_attribute = Self::read_visibility(bytes)?;
+        let gates_attribute = Self::read_visibility(bytes)?;
 
-        Self {
+        let num_entries = bytes.read_u16()?;
+        let entries = (0..num_entries).map(|_| Entry::read(bytes)).collect::<Result<_, _>>()?;
+
+        Ok(Self {
             name,
             owner_attribute,
             gates_attribute,
             entries
-        }
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        Self::write_visibility(bytes, &self.owner_attribute, "address")?;
+        Self::write_visibility(bytes, &self.gates_attribute, "u64")?;
+        util::write_count(bytes, self.entries.len(), 2, "record entries")?;
+        self.entries.iter().try_for_each(|e| e.write(bytes))
     }
 }
 
 impl Assembly for Record {
     fn assembly(&self) -> String {
-        let entries = self.entries.iter().map(|i| format!("\t{}", i.assembly())).collect::<Vec<String>>().join("\n");
-        format!("record {}:\n\towner as address.{}\n\tgates as u64.{}\n{}", self.name, self.owner_attribute.assembly(), self.gates_attribute.assembly(), entries)
+        let entries = self.entries.iter().map(|i| format!("\n    {};", i.assembly())).collect::<String>();
+        format!("record {}:\n    owner as address.{};\n    gates as u64.{};{}", self.name, self.owner_attribute.assembly(), self.gates_attribute.assembly(), entries)
     }
 
     fn leo(&self) -> String {
-        let entries = self.entries.iter().map(|i| format!("\t{}", i.leo())).collect::<Vec<String>>().join("\n");
-        format!("record {} {{\n\towner: address.{},\n\tgates: u64.{},\n{}\n}}\n", self.name, self.owner_attribute.leo(), self.gates_attribute.leo(), entries)
+        printer::item(&Item::Record(Lower::default().record(self)))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_constant_visibility() {
+        let record = |owner_attribute, gates_attribute| Record { name: "token".into(), owner_attribute, gates_attribute, entries: Vec::new() };
+        assert!(record(Attribute::Public, Attribute::Private).write(&mut Vec::new()).is_ok());
+        assert_eq!(
+            record(Attribute::Constant, Attribute::Private).write(&mut Vec::new()),
+            Err(EncodeError::MismatchedAttribute("address".into(), "constant".into())),
+        );
+        assert_eq!(
+            record(Attribute::Private, Attribute::Record).write(&mut Vec::new()),
+            Err(EncodeError::MismatchedAttribute("u64".into(), "record".into())),
+        );
+    }
+}
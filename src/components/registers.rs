@@ -1,36 +1,83 @@
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use crate::util;
 use super::function::FunctionType;
 use super::{Type, Attribute};
 use super::types;
 use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::assembler::{self, Parser, TokenKind};
+use crate::error::ParseError;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Register {
     pub(super) locator: usize,
     pub(super) identifiers: Vec<String>,
 }
 
 impl Register {
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        let f = bytes.read_u8();
-        let locator = util::read_variable_length_int(bytes);
-        let identifiers = {
-            if f == 1 {
-                util::read_identifiers(bytes)
-            } else {
-                vec![]
-            }
+    // Index of the register, 3 for `r3.owner`
+    pub fn locator(&self) -> usize {
+        self.locator
+    }
+
+    // Members accessed on the register, `["owner"]` for `r3.owner`
+    pub fn identifiers(&self) -> &[String] {
+        &self.identifiers
+    }
+
+    pub fn set_locator(&mut self, locator: usize) {
+        self.locator = locator;
+    }
+
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let start = bytes.position();
+        let f = bytes.read_u8()?;
+        let locator = util::read_variable_length_int(bytes)?;
+        let identifiers = match f {
+            0 => vec![],
+            1 => util::read_identifiers(bytes)?,
+            t => return Err(DecodeError::UnknownRegisterTag(t, bytes.location_at(start))),
         };
-        Self {locator, identifiers}
+        Ok(Self {locator, identifiers})
+    }
+
+    // `r3` or `r3.owner`, `None` when the word is not a register
+    pub(crate) fn from_word(word: &str) -> Option<Self> {
+        let mut parts = word.split('.');
+        let locator = parts.next()?.strip_prefix('r')?;
+        if locator.is_empty() || !locator.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let identifiers = parts.map(|p| assembler::is_identifier(p).then(|| p.to_string())).collect::<Option<Vec<String>>>()?;
+        Some(Self { locator: locator.parse().ok()?, identifiers })
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let start = p.position();
+        let word = p.next_word()?;
+        Self::from_word(&word).ok_or_else(|| p.error_at(start, &format!("expected a register, found `{}`", word)))
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        if self.identifiers.is_empty() {
+            bytes.push(0);
+            util::write_variable_length_int(bytes, self.locator);
+            Ok(())
+        } else {
+            bytes.push(1);
+            util::write_variable_length_int(bytes, self.locator);
+            util::write_identifiers(bytes, &self.identifiers)
+        }
     }
 }
 
 impl Assembly for Register {
     fn assembly(&self) -> String {
-        if self.identifiers.len() == 0 {
+        if self.identifiers.is_empty() {
             format!("r{}", self.locator)
         } else {
             format!("r{}.{}", self.locator, self.identifiers.join("."))
@@ -42,55 +89,172 @@ impl Assembly for Register {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IOType {
     Input,
     Output
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IoRegister {
     register: Register,
     io_type: IOType,
     function_type: FunctionType,
     value_type: Type,
-    attribute_type: Attribute,
+    // Closure registers carry no visibility
+    attribute_type: Option<Attribute>,
 }
 
 impl IoRegister {
-    pub fn read(bytes: &mut ByteCode, function_type: FunctionType, io_type: IOType) -> Self {
+    pub fn read(bytes: &mut ByteCode, function_type: FunctionType, io_type: IOType) -> Result<Self, DecodeError> {
         match function_type {
             FunctionType::Function => {
-                let register = Register::read(bytes);
-                let (value_type, attribute_type) = types::read_function_register_type(bytes);
-                Self {
+                let register = Register::read(bytes)?;
+                let (value_type, attribute_type) = types::read_function_register_type(bytes)?;
+                Ok(Self {
+                    register,
+                    io_type,
+                    function_type,
+                    value_type,
+                    attribute_type: Some(attribute_type),
+                })
+            },
+            FunctionType::Closure => {
+                let register = Register::read(bytes)?;
+                let value_type = types::read_register_type(bytes)?;
+                Ok(Self {
                     register,
                     io_type,
                     function_type,
                     value_type,
-                    attribute_type,
-                }
+                    attribute_type: None,
+                })
             },
-            FunctionType::Closure => todo!(),
-            FunctionType::Finalize => todo!(),
-            FunctionType::Uninitialized => unimplemented!(),
+            FunctionType::Finalize => {
+                // Finalize inputs are always public plaintexts
+                let register = Register::read(bytes)?;
+                let value_type = types::read_plaintext_type(bytes)?;
+                Ok(Self {
+                    register,
+                    io_type,
+                    function_type,
+                    value_type,
+                    attribute_type: Some(Attribute::Public),
+                })
+            },
+            FunctionType::Uninitialized => Err(DecodeError::Unsupported("register outside of a function", bytes.location())),
+        }
+    }
+
+    // `input r0 as u64.public;`
+    pub(crate) fn parse(p: &mut Parser, function_type: FunctionType, io_type: IOType) -> Result<Self, ParseError> {
+        p.expect_word(match io_type {
+            IOType::Input => "input",
+            IOType::Output => "output",
+        })?;
+        let register = Register::parse(p)?;
+        p.expect_word("as")?;
+        let start = p.position();
+        let (value_type, attribute_type) = match function_type {
+            FunctionType::Function => {
+                let (value_type, attribute_type) = types::parse_function_register_type(p)?;
+                (value_type, Some(attribute_type))
+            },
+            FunctionType::Closure => (types::parse_register_type(p)?, None),
+            FunctionType::Finalize => match types::parse_plaintext_attribute_type(p)? {
+                (value_type, Attribute::Public) => (value_type, Some(Attribute::Public)),
+                _ => return Err(p.error_at(start, "finalize inputs must be public")),
+            },
+            FunctionType::Uninitialized => return Err(p.error("register outside of a function")),
+        };
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(Self {
+            register,
+            io_type,
+            function_type,
+            value_type,
+            attribute_type,
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.register.write(bytes)?;
+        match (self.function_type, &self.attribute_type) {
+            (FunctionType::Function, Some(attribute)) => types::write_function_register_type(bytes, &self.value_type, attribute),
+            (FunctionType::Finalize, _) => types::write_plaintext_type(bytes, &self.value_type),
+            (FunctionType::Closure, _) => types::write_register_type(bytes, &self.value_type),
+            // Function registers always have a visibility
+            _ => Err(EncodeError::MismatchedAttribute(self.value_type.assembly(), String::from("none"))),
         }
     }
 }
 
+impl IoRegister {
+    pub fn register(&self) -> &Register {
+        &self.register
+    }
+
+    pub fn register_mut(&mut self) -> &mut Register {
+        &mut self.register
+    }
+
+    pub fn io_type(&self) -> &IOType {
+        &self.io_type
+    }
+
+    pub fn function_type(&self) -> FunctionType {
+        self.function_type
+    }
+
+    pub fn value_type(&self) -> &Type {
+        &self.value_type
+    }
+
+    pub fn attribute(&self) -> Option<&Attribute> {
+        self.attribute_type.as_ref()
+    }
+
+    // Type of the register as written in a Leo signature
+    pub fn leo_type(&self) -> String {
+        match &self.attribute_type {
+            Some(Attribute::Public) => format!("public {}", self.value_type.leo()),
+            _ => self.value_type.leo(),
+        }
+    }
+
+    // `{ "register", "type", "visibility" }`, the visibility is `null` for closures
+    pub(crate) fn abi(&self) -> Json {
+        Json::Object(vec![
+            ("register", self.register.assembly().into()),
+            ("type", self.value_type.assembly().into()),
+            ("visibility", self.attribute_type.map_or(Json::Null, |a| a.assembly().into())),
+        ])
+    }
+}
+
 impl Assembly for IoRegister {
     fn assembly(&self) -> String {
         let io = match self.io_type {
             IOType::Input => "input".to_string(),
             IOType::Output => "output".to_string(),
         };
-        format!("{} {} as {}.{}", io, self.register.assembly(), self.value_type.assembly(), self.attribute_type.assembly())
+        match &self.attribute_type {
+            // Record types already carry their `.record` suffix
+            Some(Attribute::Record) | Some(Attribute::ExternalRecord) | None => format!("{} {} as {}", io, self.register.assembly(), self.value_type.assembly()),
+            Some(attribute) => format!("{} {} as {}.{}", io, self.register.assembly(), self.value_type.assembly(), attribute.assembly()),
+        }
     }
 
     fn leo(&self) -> String {
-        match self.io_type {
-            IOType::Input => format!("{} {}: {}", self.attribute_type.leo(), self.register.leo(), self.value_type.leo()),
-            IOType::Output => self.register.leo(),
+        match (&self.io_type, &self.attribute_type, self.function_type) {
+            (IOType::Output, _, _) => self.register.leo(),
+            (IOType::Input, None, _)
+            | (IOType::Input, Some(Attribute::Record), _)
+            | (IOType::Input, Some(Attribute::ExternalRecord), _)
+            | (IOType::Input, _, FunctionType::Finalize) => format!("{}: {}", self.register.leo(), self.value_type.leo()),
+            (IOType::Input, Some(attribute), _) => format!("{} {}: {}", attribute.leo(), self.register.leo(), self.value_type.leo()),
         }
     }
 }
\ No newline at end of file
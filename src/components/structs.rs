@@ -1,15 +1,31 @@
 use crate::output::Assembly;
+use crate::output::json::Json;
+use crate::leo::{ast::Item, lower::Lower, printer};
+use crate::assembler::{Parser, TokenKind};
+use crate::error::ParseError;
 use crate::{ByteCode, util};
+use crate::error::{DecodeError, EncodeError};
 use super::types::{Type, self};
 use alloc::string::String;
 use alloc::vec::Vec;
 
-#[derive(Debug)]
-struct Entry {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
     name: String,
     value_type: Type
 }
 
+impl Entry {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value_type(&self) -> &Type {
+        &self.value_type
+    }
+}
+
 impl Assembly for Entry {
     fn assembly(&self) -> String {
         format!("{} as {}", self.name, self.value_type.assembly())
@@ -20,37 +36,89 @@ impl Assembly for Entry {
      }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Struct {
     name: String,
     entries: Vec<Entry>,
 }
 
 impl Struct {
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        let name = util::read_identifier(bytes);
-        let num_entries = bytes.read_u16();
-        let entries: Vec<Entry> = (0..num_entries)
-            .map(|_| Entry{
-                name: util::read_identifier(bytes),
-                value_type: types::read_plaintext_type(bytes),
-            })
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    // Type of the member `name`
+    pub fn member(&self, name: &str) -> Option<&Type> {
+        self.entries.iter().find(|e| e.name == name).map(|e| &e.value_type)
+    }
+
+    pub(crate) fn abi(&self) -> Json {
+        let members = self.entries.iter()
+            .map(|e| Json::Object(vec![("name", e.name.as_str().into()), ("type", e.value_type.assembly().into())]))
             .collect();
-        Self {
+        Json::Object(vec![
+            ("name", self.name.as_str().into()),
+            ("members", Json::Array(members)),
+        ])
+    }
+
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let name = util::read_identifier(bytes)?;
+        bytes.set_context(&name);
+        let num_entries = bytes.read_u16()?;
+        let entries: Vec<Entry> = (0..num_entries)
+            .map(|_| Ok(Entry{
+                name: util::read_identifier(bytes)?,
+                value_type: types::read_plaintext_type(bytes)?,
+            }))
+            .collect::<Result<_, DecodeError>>()?;
+        Ok(Self {
+            name,
+            entries
+        })
+    }
+
+    // `name:` followed by the members, after the `struct` keyword
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let name = p.identifier()?;
+        p.expect(&TokenKind::Colon)?;
+        let mut entries = Vec::new();
+        while p.peek_nth(1) == Some(&TokenKind::Word(String::from("as"))) {
+            let name = p.identifier()?;
+            p.expect_word("as")?;
+            let value_type = types::parse_plaintext_type(p)?;
+            p.expect(&TokenKind::Semicolon)?;
+            entries.push(Entry { name, value_type });
+        }
+        Ok(Self {
             name,
             entries
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        util::write_identifier(bytes, &self.name)?;
+        util::write_count(bytes, self.entries.len(), 2, "struct members")?;
+        for entry in self.entries.iter() {
+            util::write_identifier(bytes, &entry.name)?;
+            types::write_plaintext_type(bytes, &entry.value_type)?;
         }
+        Ok(())
     }
 }
 
 impl Assembly for Struct {
     fn assembly(&self) -> String {
-        let entries = self.entries.iter().map(|i| format!("\t{};", i.assembly())).collect::<Vec<String>>().join("\n"); 
-        format!("struct {}:\n{}\n", self.name, entries)
+        let entries = self.entries.iter().map(|i| format!("\n    {};", i.assembly())).collect::<String>();
+        format!("struct {}:{}", self.name, entries)
     }
 
     fn leo(&self) -> String {
-        let entries = self.entries.iter().map(|i| format!("\t{}", i.leo())).collect::<Vec<String>>().join("\n"); 
-        format!("struct {} {{\n{}\n}}\n", self.name, entries)
-     }
+        printer::item(&Item::Struct(Lower::default().structure(self)))
+    }
 }
\ No newline at end of file
@@ -2,10 +2,15 @@ use core::fmt::Debug;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use crate::util;
+use crate::bech32;
 use crate::output::Assembly;
+use crate::assembler::{self, Parser, TokenKind};
+use crate::error::ParseError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     // The Address type
     Address,
@@ -40,7 +45,11 @@ pub enum Type {
     // The string type
     String,
     // User defined Type
-    Other(String)
+    Other(String),
+    // Record defined in this program
+    Record(String),
+    // Record defined in an imported program, as (program, network, record)
+    ExternalRecord((String, String, String)),
 }
 
 impl Assembly for Type {
@@ -63,17 +72,50 @@ impl Assembly for Type {
             Self::Scalar =>  "scalar".to_string(),
             Self::String => "string".to_string(),
             Self::Other(s) => s.clone(),
+            Self::Record(r) => format!("{}.record", r),
+            Self::ExternalRecord((p, n, r)) => format!("{}.{}/{}.record", p, n, r),
         }
     }
 
     fn leo(&self) -> String {
-        return self.assembly();
-     }
+        match self {
+            Self::Record(r) => r.clone(),
+            Self::ExternalRecord((p, n, r)) => format!("{}.{}/{}", p, n, r),
+            _ => self.assembly(),
+        }
+    }
 }
 
-impl From<u16> for Type {
-    fn from(value: u16) -> Self {
-        match value {
+impl Type {
+    // Id of a literal type in the bytecode, `None` for user defined and record types
+    fn literal_type_id(&self) -> Option<u16> {
+        Some(match self {
+            Self::Address => 0,
+            Self::Boolean => 1,
+            Self::Field => 2,
+            Self::Group => 3,
+            Self::I8 => 4,
+            Self::I16 => 5,
+            Self::I32 => 6,
+            Self::I64 => 7,
+            Self::I128 => 8,
+            Self::U8 => 9,
+            Self::U16 => 10,
+            Self::U32 => 11,
+            Self::U64 => 12,
+            Self::U128 => 13,
+            Self::Scalar => 14,
+            Self::String => 15,
+            Self::Other(_) | Self::Record(_) | Self::ExternalRecord(_) => return None,
+        })
+    }
+}
+
+impl TryFrom<u16> for Type {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
             0 => Self::Address,
             1 => Self::Boolean,
             2 => Self::Field,
@@ -90,12 +132,13 @@ impl From<u16> for Type {
             13 => Self::U128,
             14 => Self::Scalar,
             15 => Self::String,
-            _ => unreachable!()
-        }
+            _ => return Err(value),
+        })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Attribute {
     Constant,
     Public,
@@ -104,16 +147,18 @@ pub enum Attribute {
     ExternalRecord,
 }
 
-impl From<usize> for Attribute {
-    fn from(value: usize) -> Self {
-        match value {
+impl TryFrom<u8> for Attribute {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
             0 => Self::Constant,
             1 => Self::Public,
             2 => Self::Private,
             3 => Self::Record,
             4 => Self::ExternalRecord,
-            _ => unreachable!()
-        }
+            _ => return Err(value),
+        })
     }
 }
 
@@ -128,15 +173,18 @@ impl Assembly for Attribute {
     }
 
     fn leo(&self) -> String {
-        return self.assembly();
-     }
+        self.assembly()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     Address(Vec<u8>),
     Boolean(bool),
     Field(Vec<u8>),
+    // x-coordinate of the affine point, the y-coordinate is recovered from it
+    Group(Vec<u8>),
     I8(i8),
     I16(i16),
     I32(i32),
@@ -148,45 +196,132 @@ pub enum Literal {
     U64(u64),
     U128(u128),
     Scalar(Vec<u8>),
+    String(String),
 }
 
 impl Literal {
-    pub fn read(bytes: &mut ByteCode) -> Self {
-        let literal_type  = bytes.read_u16();
-        match literal_type {
+    pub fn read(bytes: &mut ByteCode) -> Result<Self, DecodeError> {
+        let start = bytes.position();
+        let literal_type  = bytes.read_u16()?;
+        Ok(match literal_type {
             // Address
-            0 => Literal::Address(bytes.read_n(32)),
+            0 => Literal::Address(bytes.read_n(32)?.to_vec()),
             // Boolean
-            1 => Literal::Boolean(bytes.read_u8() != 0),
+            1 => match bytes.read_u8()? {
+                0 => Literal::Boolean(false),
+                1 => Literal::Boolean(true),
+                b => return Err(DecodeError::InvalidBoolean(b, bytes.location_at(bytes.position() - 1))),
+            },
             // Field
-            2 => Literal::Field(bytes.read_n(32)),
-            // No group literals allowed
-            3 => unreachable!(),
+            2 => Literal::Field(bytes.read_n(32)?.to_vec()),
+            // Group
+            3 => Literal::Group(bytes.read_n(32)?.to_vec()),
             // I8
-            4 => Literal::I8(bytes.read_i8()),
+            4 => Literal::I8(bytes.read_i8()?),
             // I16
-            5 => Literal::I16(bytes.read_i16()),
+            5 => Literal::I16(bytes.read_i16()?),
             // I32
-            6 => Literal::I32(bytes.read_i32()),
+            6 => Literal::I32(bytes.read_i32()?),
             // I64
-            7 => Literal::I64(bytes.read_i64()),
+            7 => Literal::I64(bytes.read_i64()?),
             // I128
-            8 => Literal::I128(bytes.read_i128()),
+            8 => Literal::I128(bytes.read_i128()?),
             // U8
-            9 => Literal::U8(bytes.read_u8()),
+            9 => Literal::U8(bytes.read_u8()?),
             // U16
-            10 => Literal::U16(bytes.read_u16()),
+            10 => Literal::U16(bytes.read_u16()?),
             // U32
-            11 => Literal::U32(bytes.read_u32()),
+            11 => Literal::U32(bytes.read_u32()?),
             // U64
-            12 => Literal::U64(bytes.read_u64()),
+            12 => Literal::U64(bytes.read_u64()?),
             // U128
-            13 => Literal::U128(bytes.read_u128()),
+            13 => Literal::U128(bytes.read_u128()?),
             // Scalar
-            14 => Literal::Scalar(bytes.read_n(32)),
+            14 => Literal::Scalar(bytes.read_n(32)?.to_vec()),
+            // String
+            15 => {
+                let n = bytes.read_u16()?;
+                let string_start = bytes.position();
+                let string = core::str::from_utf8(bytes.read_n(n as usize)?)
+                    .map_err(|_| DecodeError::InvalidUtf8String(bytes.location_at(string_start)))?;
+                Literal::String(string.to_string())
+            },
             // Unhandled Literal Type
-            _ => unreachable!()
+            t => return Err(DecodeError::UnknownLiteralType(t, bytes.location_at(start))),
+        })
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<Self, ParseError> {
+        let start = p.position();
+        let word = match p.next() {
+            Some(TokenKind::String(s)) => return Ok(Literal::String(s)),
+            Some(TokenKind::Word(w)) => w,
+            _ => return Err(p.error_at(start, "expected a literal")),
+        };
+        let error = |message: &str| p.error_at(start, &format!("{} `{}`", message, word));
+        match word.as_str() {
+            "true" => return Ok(Literal::Boolean(true)),
+            "false" => return Ok(Literal::Boolean(false)),
+            w if w.starts_with(bech32::ADDRESS_PREFIX) => {
+                return bech32::decode_address(w).map(Literal::Address).map_err(|e| error(&format!("{} in address", e)));
+            },
+            _ => {},
+        }
+        // Digits, with an optional sign, followed by the type
+        let split = word.char_indices().find(|(i, c)| c.is_ascii_alphabetic() && *i > 0).map(|(i, _)| i).ok_or_else(|| error("invalid literal"))?;
+        let (value, suffix) = word.split_at(split);
+        if value.is_empty() || !value.trim_start_matches('-').chars().all(|c| c.is_ascii_digit()) {
+            return Err(error("invalid literal"));
+        }
+        let element = |value: &str| decimal_to_le_bytes(value, 32).ok_or_else(|| error("invalid literal"));
+        let integer = || error("integer out of range in literal");
+        Ok(match suffix {
+            "field" => Literal::Field(element(value)?),
+            "group" => Literal::Group(element(value)?),
+            "scalar" => Literal::Scalar(element(value)?),
+            "i8" => Literal::I8(value.parse().map_err(|_| integer())?),
+            "i16" => Literal::I16(value.parse().map_err(|_| integer())?),
+            "i32" => Literal::I32(value.parse().map_err(|_| integer())?),
+            "i64" => Literal::I64(value.parse().map_err(|_| integer())?),
+            "i128" => Literal::I128(value.parse().map_err(|_| integer())?),
+            "u8" => Literal::U8(value.parse().map_err(|_| integer())?),
+            "u16" => Literal::U16(value.parse().map_err(|_| integer())?),
+            "u32" => Literal::U32(value.parse().map_err(|_| integer())?),
+            "u64" => Literal::U64(value.parse().map_err(|_| integer())?),
+            "u128" => Literal::U128(value.parse().map_err(|_| integer())?),
+            _ => return Err(error("unknown literal type in")),
+        })
+    }
+
+    pub fn write(&self, bytes: &mut Vec<u8>) -> Result<(), EncodeError> {
+        // Every literal has a literal type
+        let literal_type = self.literal_type().literal_type_id().unwrap_or_default();
+        bytes.extend_from_slice(&literal_type.to_le_bytes());
+        match self {
+            Self::Address(b) | Self::Field(b) | Self::Group(b) | Self::Scalar(b) => {
+                if b.len() != 32 {
+                    return Err(EncodeError::InvalidLiteralLength(b.len()));
+                }
+                bytes.extend_from_slice(b);
+            },
+            Self::Boolean(b) => bytes.push(*b as u8),
+            Self::I8(i) => bytes.extend_from_slice(&i.to_le_bytes()),
+            Self::I16(i) => bytes.extend_from_slice(&i.to_le_bytes()),
+            Self::I32(i) => bytes.extend_from_slice(&i.to_le_bytes()),
+            Self::I64(i) => bytes.extend_from_slice(&i.to_le_bytes()),
+            Self::I128(i) => bytes.extend_from_slice(&i.to_le_bytes()),
+            Self::U8(u) => bytes.push(*u),
+            Self::U16(u) => bytes.extend_from_slice(&u.to_le_bytes()),
+            Self::U32(u) => bytes.extend_from_slice(&u.to_le_bytes()),
+            Self::U64(u) => bytes.extend_from_slice(&u.to_le_bytes()),
+            Self::U128(u) => bytes.extend_from_slice(&u.to_le_bytes()),
+            Self::String(s) => {
+                let len = u16::try_from(s.len()).map_err(|_| EncodeError::StringTooLong(s.len()))?;
+                bytes.extend_from_slice(&len.to_le_bytes());
+                bytes.extend_from_slice(s.as_bytes());
+            },
         }
+        Ok(())
     }
 
     pub fn literal_type(&self) -> Type {
@@ -194,6 +329,7 @@ impl Literal {
             Self::Address(_) =>  Type::Address,
             Self::Boolean(_) =>  Type::Boolean,
             Self::Field(_) =>  Type::Field,
+            Self::Group(_) =>  Type::Group,
             Self::I8(_) =>  Type::I8,
             Self::I16(_) =>  Type::I16,
             Self::I32(_) =>  Type::I32,
@@ -205,6 +341,7 @@ impl Literal {
             Self::U64(_) =>  Type::U64,
             Self::U128(_) =>  Type::U128,
             Self::Scalar(_) =>  Type::Scalar,
+            Self::String(_) =>  Type::String,
         }
     }
 }
@@ -212,47 +349,269 @@ impl Literal {
 impl Assembly for Literal {
     fn assembly(&self) -> String {
         match self {
-            Self::Address(ad) =>  ad.iter().map(|x| format!("{:02x?}", x)).collect::<Vec<String>>().join(""),
+            Self::Address(ad) =>  bech32::encode_address(ad),
             Self::Boolean(b) =>  b.to_string(),
-            Self::Field(f) =>  { f.clone().reverse(); f.iter().map(|x| format!("{:02x?}", x)).collect::<Vec<String>>().join("") },
-            Self::I8(i) =>  i.to_string(),
-            Self::I16(i) =>  i.to_string(),
-            Self::I32(i) =>  i.to_string(),
-            Self::I64(i) =>  i.to_string(),
-            Self::I128(i) =>  i.to_string(),
-            Self::U8(u) =>  u.to_string(),
-            Self::U16(u) =>  u.to_string(),
-            Self::U32(u) =>  u.to_string(),
-            Self::U64(u) =>  u.to_string(),
-            Self::U128(u) =>  u.to_string(),
-            Self::Scalar(s) => { s.clone().reverse(); s.iter().map(|x| format!("{:02x?}", x)).collect::<Vec<String>>().join("") },
+            Self::Field(f) =>  format!("{}field", le_bytes_to_decimal(f)),
+            Self::Group(g) =>  format!("{}group", le_bytes_to_decimal(g)),
+            Self::I8(i) =>  format!("{}i8", i),
+            Self::I16(i) =>  format!("{}i16", i),
+            Self::I32(i) =>  format!("{}i32", i),
+            Self::I64(i) =>  format!("{}i64", i),
+            Self::I128(i) =>  format!("{}i128", i),
+            Self::U8(u) =>  format!("{}u8", u),
+            Self::U16(u) =>  format!("{}u16", u),
+            Self::U32(u) =>  format!("{}u32", u),
+            Self::U64(u) =>  format!("{}u64", u),
+            Self::U128(u) =>  format!("{}u128", u),
+            Self::Scalar(s) =>  format!("{}scalar", le_bytes_to_decimal(s)),
+            Self::String(s) => format!("\"{}\"", escape_string(s)),
         }
     }
 
     fn leo(&self) -> String {
-        return self.assembly();
-     }
+        self.assembly()
+    }
+}
+
+// Decimal representation of a little-endian unsigned integer of any width,
+// used for the 256-bit field, group and scalar elements
+pub fn le_bytes_to_decimal(bytes: &[u8]) -> String {
+    // 32-bit limbs, most significant first
+    let mut limbs: Vec<u32> = bytes
+        .chunks(4)
+        .rev()
+        .map(|chunk| {
+            let mut limb = [0u8; 4];
+            limb[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(limb)
+        })
+        .collect();
+    // Repeatedly divide by 10^9, the remainders are the base 10^9 digits, least significant first
+    let mut digits: Vec<u32> = Vec::new();
+    while limbs.iter().any(|l| *l != 0) {
+        let mut remainder: u64 = 0;
+        for limb in limbs.iter_mut() {
+            let current = (remainder << 32) | *limb as u64;
+            *limb = (current / 1_000_000_000) as u32;
+            remainder = current % 1_000_000_000;
+        }
+        digits.push(remainder as u32);
+    }
+    let mut o = match digits.pop() {
+        Some(d) => d.to_string(),
+        None => return "0".to_string(),
+    };
+    for d in digits.iter().rev() {
+        o.push_str(&format!("{:09}", d));
+    }
+    o
+}
+
+// Little-endian bytes of a non-negative decimal number, `None` when it does not fit in `n` bytes
+pub fn decimal_to_le_bytes(decimal: &str, n: usize) -> Option<Vec<u8>> {
+    let mut bytes = vec![0u8; n];
+    for digit in decimal.chars() {
+        // bytes = bytes * 10 + digit
+        let mut carry = digit.to_digit(10)?;
+        for b in bytes.iter_mut() {
+            let v = *b as u32 * 10 + carry;
+            *b = v as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(bytes)
+}
+
+// Escape a string so it can be written back as a quoted literal
+pub fn escape_string(s: &str) -> String {
+    let mut o = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => o.push_str("\\\""),
+            '\\' => o.push_str("\\\\"),
+            '\n' => o.push_str("\\n"),
+            '\r' => o.push_str("\\r"),
+            '\t' => o.push_str("\\t"),
+            '\0' => o.push_str("\\0"),
+            c if c.is_control() => o.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => o.push(c),
+        }
+    }
+    o
 }
 
-pub fn read_attribute(bytes: &mut ByteCode) -> Attribute {
-    Attribute::from(bytes.read_u8() as usize)
+pub fn read_attribute(bytes: &mut ByteCode) -> Result<Attribute, DecodeError> {
+    let start = bytes.position();
+    let attribute = bytes.read_u8()?;
+    Attribute::try_from(attribute).map_err(|a| DecodeError::UnknownAttribute(a, bytes.location_at(start)))
 }
 
-pub fn read_plaintext_type(bytes: &mut ByteCode) -> Type {
-    match bytes.read_u8() {
+pub fn read_plaintext_type(bytes: &mut ByteCode) -> Result<Type, DecodeError> {
+    let start = bytes.position();
+    match bytes.read_u8()? {
         // Literal Type
-        0 => Type::from(bytes.read_u16()),
+        0 => {
+            let start = bytes.position();
+            let literal_type = bytes.read_u16()?;
+            Type::try_from(literal_type).map_err(|t| DecodeError::UnknownLiteralType(t, bytes.location_at(start)))
+        },
         // Identifier for user defined type
-        1 => Type::Other(util::read_identifier(bytes)),
-        _ => unreachable!(),
+        1 => Ok(Type::Other(util::read_identifier(bytes)?)),
+        t => Err(DecodeError::UnknownTypeTag(t, bytes.location_at(start))),
     }
 }
 
-pub fn read_function_register_type(bytes: &mut ByteCode) -> (Type, Attribute) {
-    let attribute = read_attribute(bytes);
+pub fn read_function_register_type(bytes: &mut ByteCode) -> Result<(Type, Attribute), DecodeError> {
+    let attribute = read_attribute(bytes)?;
     let value_type = match attribute {
-        Attribute::Private | Attribute::Public | Attribute::Constant => read_plaintext_type(bytes),
-        _ => todo!(),
+        Attribute::Private | Attribute::Public | Attribute::Constant => read_plaintext_type(bytes)?,
+        Attribute::Record => Type::Record(util::read_identifier(bytes)?),
+        Attribute::ExternalRecord => Type::ExternalRecord(util::read_locator(bytes)?),
     };
-    (value_type, attribute)
+    Ok((value_type, attribute))
+}
+
+pub fn read_register_type(bytes: &mut ByteCode) -> Result<Type, DecodeError> {
+    let start = bytes.position();
+    match bytes.read_u8()? {
+        // Plaintext
+        0 => read_plaintext_type(bytes),
+        // Record
+        1 => Ok(Type::Record(util::read_identifier(bytes)?)),
+        // External record
+        2 => Ok(Type::ExternalRecord(util::read_locator(bytes)?)),
+        t => Err(DecodeError::UnknownTypeTag(t, bytes.location_at(start))),
+    }
+}
+
+pub fn write_attribute(bytes: &mut Vec<u8>, attribute: &Attribute) {
+    bytes.push(match attribute {
+        Attribute::Constant => 0,
+        Attribute::Public => 1,
+        Attribute::Private => 2,
+        Attribute::Record => 3,
+        Attribute::ExternalRecord => 4,
+    });
+}
+
+pub fn write_plaintext_type(bytes: &mut Vec<u8>, value_type: &Type) -> Result<(), EncodeError> {
+    match (value_type, value_type.literal_type_id()) {
+        (_, Some(literal_type)) => {
+            bytes.push(0);
+            bytes.extend_from_slice(&literal_type.to_le_bytes());
+            Ok(())
+        },
+        (Type::Other(name), None) => {
+            bytes.push(1);
+            util::write_identifier(bytes, name)
+        },
+        _ => Err(EncodeError::NotPlaintext(value_type.assembly())),
+    }
+}
+
+// The attribute says how the type is encoded, so the two have to agree
+pub fn write_function_register_type(bytes: &mut Vec<u8>, value_type: &Type, attribute: &Attribute) -> Result<(), EncodeError> {
+    write_attribute(bytes, attribute);
+    match (value_type, attribute) {
+        (Type::Record(name), Attribute::Record) => util::write_identifier(bytes, name),
+        (Type::ExternalRecord((p, n, r)), Attribute::ExternalRecord) => util::write_locator(bytes, (p, n, r)),
+        (_, Attribute::Constant | Attribute::Public | Attribute::Private) => write_plaintext_type(bytes, value_type),
+        _ => Err(EncodeError::MismatchedAttribute(value_type.assembly(), attribute.assembly())),
+    }
+}
+
+pub fn write_register_type(bytes: &mut Vec<u8>, value_type: &Type) -> Result<(), EncodeError> {
+    match value_type {
+        Type::Record(name) => {
+            bytes.push(1);
+            util::write_identifier(bytes, name)
+        },
+        Type::ExternalRecord((p, n, r)) => {
+            bytes.push(2);
+            util::write_locator(bytes, (p, n, r))
+        },
+        _ => {
+            bytes.push(0);
+            write_plaintext_type(bytes, value_type)
+        },
+    }
+}
+
+// Literal type or the name of a struct
+fn plaintext_type(name: &str) -> Option<Type> {
+    match (0..=15).filter_map(|i| Type::try_from(i).ok()).find(|t| t.assembly() == name) {
+        Some(t) => Some(t),
+        None if assembler::is_identifier(name) => Some(Type::Other(name.to_string())),
+        None => None,
+    }
+}
+
+pub(crate) fn parse_plaintext_type(p: &mut Parser) -> Result<Type, ParseError> {
+    let start = p.position();
+    let word = p.next_word()?;
+    plaintext_type(&word).ok_or_else(|| p.error_at(start, &format!("unknown type `{}`", word)))
+}
+
+// `u64.public`, `token.record` or `other.aleo/token.record`
+pub(crate) fn parse_function_register_type(p: &mut Parser) -> Result<(Type, Attribute), ParseError> {
+    let start = p.position();
+    let word = p.next_word()?;
+    let error = |message: &str| p.error_at(start, &format!("{} `{}`", message, word));
+    if let Some(locator) = word.strip_suffix(".record").filter(|w| w.contains('/')) {
+        let locator = assembler::split_locator(locator).ok_or_else(|| error("invalid external record"))?;
+        return Ok((Type::ExternalRecord(locator), Attribute::ExternalRecord));
+    }
+    let (name, attribute) = word.rsplit_once('.').ok_or_else(|| error("missing visibility in"))?;
+    let attribute = match attribute {
+        "constant" => Attribute::Constant,
+        "public" => Attribute::Public,
+        "private" => Attribute::Private,
+        "record" if assembler::is_identifier(name) => return Ok((Type::Record(name.to_string()), Attribute::Record)),
+        _ => return Err(error("unknown visibility in")),
+    };
+    let value_type = plaintext_type(name).ok_or_else(|| error("unknown type in"))?;
+    Ok((value_type, attribute))
+}
+
+// Plaintext type with its visibility, e.g. the entries of records and mappings
+pub(crate) fn parse_plaintext_attribute_type(p: &mut Parser) -> Result<(Type, Attribute), ParseError> {
+    let start = p.position();
+    match parse_function_register_type(p)? {
+        (_, Attribute::Record) | (_, Attribute::ExternalRecord) => Err(p.error_at(start, "expected a plaintext type")),
+        t => Ok(t),
+    }
+}
+
+// Register type of closures and casts: a plaintext type or a record without visibility
+pub(crate) fn parse_register_type(p: &mut Parser) -> Result<Type, ParseError> {
+    let start = p.position();
+    let word = p.next_word()?;
+    let error = |message: &str| p.error_at(start, &format!("{} `{}`", message, word));
+    match word.strip_suffix(".record") {
+        Some(locator) if locator.contains('/') => assembler::split_locator(locator).map(Type::ExternalRecord).ok_or_else(|| error("invalid external record")),
+        Some(name) if assembler::is_identifier(name) => Ok(Type::Record(name.to_string())),
+        Some(_) => Err(error("invalid record")),
+        None => plaintext_type(&word).ok_or_else(|| error("unknown type")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn le_bytes_to_decimal_edges() {
+        const MAX: &str = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(le_bytes_to_decimal(&[]), "0");
+        assert_eq!(le_bytes_to_decimal(&[0; 32]), "0");
+        assert_eq!(le_bytes_to_decimal(&[0, 0, 0, 0, 0, 0, 0, 0, 1]), "18446744073709551616");
+        assert_eq!(le_bytes_to_decimal(&[0xff; 32]), MAX);
+        // Limbs holding a single digit group must keep their leading zeros
+        assert_eq!(le_bytes_to_decimal(&1_000_000_007u64.to_le_bytes()), "1000000007");
+        assert_eq!(decimal_to_le_bytes(MAX, 32), Some(vec![0xff; 32]));
+        assert_eq!(decimal_to_le_bytes("18446744073709551616", 8), None);
+    }
 }
@@ -1,54 +1,262 @@
 use core::fmt::Write;
 use crate::bytecode::ByteCode;
+use crate::error::{DecodeError, EncodeError, ParseError};
+use crate::assembler::{Parser, TokenKind};
 use crate::util;
-use crate::components::{ Function, Mapping, Struct, Record };
+use crate::components::{ Component, Function, Mapping, Struct, Record };
 use crate::components::function::FunctionType;
+use crate::components::instructions::Locator;
+use crate::components::commands::{Command, CommandLayout};
 use crate::output::Assembly;
-use alloc::{vec::Vec, string::String};
+use crate::output::json::Json;
+use crate::inference::Infer;
+use crate::leo::LeoOptions;
+use alloc::{vec::Vec, string::{String, ToString}};
 
 #[derive(Default, Debug)]
 pub struct Disassembler {
-    bytes: ByteCode,
+    bytes: Vec<u8>,
     version: u16,
     program_name: String,
     network: String,
     num_imports: u8,
     imports: Vec<(String, String)>,
     num_components: u16,
-    functions: Vec<Function>,
-    mappings: Vec<Mapping>,
-    structs: Vec<Struct>,
-    records: Vec<Record>,
+    // Every component in declaration order
+    components: Vec<Component>,
+    // Positions in `components` of each kind
+    functions: Vec<usize>,
+    mappings: Vec<usize>,
+    structs: Vec<usize>,
+    records: Vec<usize>,
+    warnings: Vec<String>,
 }
 
 impl Disassembler {
     pub fn from_bytes(buf: Vec<u8>) -> Self {
-        Self { bytes: ByteCode::new(buf), ..Default::default() }
+        Self { bytes: buf, ..Default::default() }
     }
 
-    fn read_header(&mut self) {
-        self.version = self.bytes.read_u16();
-        (self.program_name, self.network) = util::read_programid(&mut self.bytes);
-        self.num_imports = self.bytes.read_u8();
-        self.imports = (0..self.num_imports).map(|_| util::read_programid(&mut self.bytes)).collect();
+    fn read_header(&mut self, bytes: &mut ByteCode) -> Result<(), DecodeError> {
+        self.version = bytes.read_u16()?;
+        (self.program_name, self.network) = util::read_programid(bytes)?;
+        self.num_imports = bytes.read_u8()?;
+        self.imports = (0..self.num_imports).map(|_| util::read_programid(bytes)).collect::<Result<_, _>>()?;
+        Ok(())
     }
 
-    fn read_num_components(&mut self) {
-        self.num_components = self.bytes.read_u16();
+    fn read_num_components(&mut self, bytes: &mut ByteCode) -> Result<(), DecodeError> {
+        self.num_components = bytes.read_u16()?;
+        Ok(())
     }
 
-    fn read_components(&mut self) {
+    fn read_components(&mut self, bytes: &mut ByteCode) -> Result<(), DecodeError> {
         for _ in 0..self.num_components {
-            let x = self.bytes.read_u8();
-            match x {
-                0 => self.mappings.push(Mapping::read(&mut self.bytes)),
-                1 => self.structs.push(Struct::read(&mut self.bytes)),
-                2 => self.records.push(Record::read(&mut self.bytes)),
-                3 => self.functions.push(Function::read(&mut self.bytes, FunctionType::Closure)),
-                4 => self.functions.push(Function::read(&mut self.bytes, FunctionType::Function)),
-                _ => unimplemented!()
+            bytes.set_context("");
+            let x = bytes.read_u8()?;
+            let component = match x {
+                0 => Component::Mapping(Mapping::read(bytes)?),
+                1 => Component::Struct(Struct::read(bytes)?),
+                2 => Component::Record(Record::read(bytes)?),
+                3 => Component::Function(Function::read(bytes, FunctionType::Closure)?),
+                4 => {
+                    let start = bytes.position();
+                    let function = Function::read(bytes, FunctionType::Function)?;
+                    self.check_mappings(&function).map_err(|m| DecodeError::UnknownMapping(m, bytes.location_at(start)))?;
+                    Component::Function(function)
+                },
+                t => return Err(DecodeError::UnknownComponentTag(t, bytes.location_at(bytes.position() - 1))),
+            };
+            self.push_component(component);
+        }
+        Ok(())
+    }
+
+    // Build the program from its Aleo instructions source instead of its bytecode
+    pub fn from_aleo(source: &str) -> Result<Self, ParseError> {
+        let mut p = Parser::new(source)?;
+        let mut a = Self::default();
+        a.parse_header(&mut p)?;
+        a.parse_components(&mut p)?;
+        a.check_imports();
+        Ok(a)
+    }
+
+    fn parse_header(&mut self, p: &mut Parser) -> Result<(), ParseError> {
+        while p.eat_word("import") {
+            self.imports.push(p.programid()?);
+            p.expect(&TokenKind::Semicolon)?;
+        }
+        self.num_imports = self.imports.len() as u8;
+        p.expect_word("program")?;
+        (self.program_name, self.network) = p.programid()?;
+        p.expect(&TokenKind::Semicolon)?;
+        Ok(())
+    }
+
+    fn parse_components(&mut self, p: &mut Parser) -> Result<(), ParseError> {
+        while !p.is_empty() {
+            let start = p.position();
+            let component = match p.next_word().as_deref() {
+                Ok("mapping") => Component::Mapping(Mapping::parse(p)?),
+                Ok("struct") => Component::Struct(Struct::parse(p)?),
+                Ok("record") => Component::Record(Record::parse(p)?),
+                Ok("closure") => Component::Function(Function::parse(p, FunctionType::Closure)?),
+                Ok("function") => {
+                    let function = Function::parse(p, FunctionType::Function)?;
+                    self.check_mappings(&function).map_err(|m| p.error_at(start, &format!("unknown mapping `{}`", m)))?;
+                    Component::Function(function)
+                },
+                Ok("finalize") => return Err(p.error_at(start, "finalize block without a `finalize` statement in the function before it")),
+                _ => return Err(p.error_at(start, "expected `mapping`, `struct`, `record`, `closure` or `function`")),
+            };
+            self.push_component(component);
+        }
+        self.num_components = self.components.len() as u16;
+        Ok(())
+    }
+
+    // Every mapping used in finalize must be declared before the function
+    fn check_mappings(&self, function: &Function) -> Result<(), String> {
+        let commands = function.finalize().map(|f| f.commands()).unwrap_or_default();
+        match commands.iter().filter_map(|c| c.mapping()).find(|m| self.mapping(m).is_none()) {
+            Some(m) => Err(m.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    // Calls into programs missing from the import list are reported, not rejected
+    fn check_imports(&mut self) {
+        let mut warnings = Vec::new();
+        for function in self.functions() {
+            // snarkVM rejects calls in finalize blocks, but the model can still hold one
+            let finalize = function.finalize().into_iter().flat_map(|f| f.commands()).filter_map(|c| match c {
+                Command::Instruction(i) => Some(i),
+                _ => None,
+            });
+            for callee in function.instructions().iter().chain(finalize).filter_map(|i| i.callee()) {
+                if let Locator::External((program, network, _)) = callee {
+                    if !self.imports.iter().any(|(p, n)| p == program && n == network) {
+                        warnings.push(format!("`{}` calls `{}` but `{}.{}` is not imported", function.name(), callee.assembly(), program, network));
+                    }
+                }
             }
         }
+        self.warnings.append(&mut warnings);
+    }
+
+    // Encode the program back into bytecode, failing on anything the format cannot represent
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut bytes = Vec::with_capacity(self.bytes.len());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        util::write_programid(&mut bytes, (&self.program_name, &self.network))?;
+        util::write_count(&mut bytes, self.imports.len(), 1, "imports")?;
+        for (program, network) in self.imports.iter() {
+            util::write_programid(&mut bytes, (program, network))?;
+        }
+        util::write_count(&mut bytes, self.components.len(), 2, "components")?;
+        self.components.iter().try_for_each(|c| c.write(&mut bytes))?;
+        Ok(bytes)
+    }
+
+    // Program rebuilt from already decoded parts, e.g. a deserialized model
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_parts(version: u16, program_id: (String, String), imports: Vec<(String, String)>, components: Vec<Component>, warnings: Vec<String>) -> Self {
+        let mut a = Self {
+            version,
+            program_name: program_id.0,
+            network: program_id.1,
+            num_imports: imports.len() as u8,
+            imports,
+            num_components: components.len() as u16,
+            warnings,
+            ..Default::default()
+        };
+        components.into_iter().for_each(|c| a.push_component(c));
+        a
+    }
+
+    fn push_component(&mut self, component: Component) {
+        let index = self.components.len();
+        match component {
+            Component::Mapping(_) => self.mappings.push(index),
+            Component::Struct(_) => self.structs.push(index),
+            Component::Record(_) => self.records.push(index),
+            Component::Function(_) => self.functions.push(index),
+        }
+        self.components.push(component);
+    }
+
+    // Components in declaration order
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    pub(crate) fn components_mut(&mut self) -> &mut [Component] {
+        &mut self.components
+    }
+
+    // Rebuild the per-kind positions after components were modified in place
+    pub(crate) fn reindex(&mut self) {
+        let components = core::mem::take(&mut self.components);
+        (self.functions, self.mappings, self.structs, self.records) = Default::default();
+        components.into_iter().for_each(|c| self.push_component(c));
+    }
+
+    // The `n`th declared component, whatever its kind
+    pub fn component(&self, n: usize) -> Option<&Component> {
+        self.components.get(n)
+    }
+
+    // Functions and closures in declaration order
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.functions.iter().filter_map(|i| match &self.components[*i] {
+            Component::Function(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = &Mapping> {
+        self.mappings.iter().filter_map(|i| match &self.components[*i] {
+            Component::Mapping(m) => Some(m),
+            _ => None,
+        })
+    }
+
+    pub fn structs(&self) -> impl Iterator<Item = &Struct> {
+        self.structs.iter().filter_map(|i| match &self.components[*i] {
+            Component::Struct(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter().filter_map(|i| match &self.components[*i] {
+            Component::Record(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    // Non-fatal issues found while decoding
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions().find(|f| f.name() == name)
+    }
+
+    pub fn mapping(&self, name: &str) -> Option<&Mapping> {
+        self.mappings().find(|m| m.name() == name)
+    }
+
+    // Named `structure` as `struct` is a keyword
+    pub fn structure(&self, name: &str) -> Option<&Struct> {
+        self.structs().find(|s| s.name() == name)
+    }
+
+    pub fn record(&self, name: &str) -> Option<&Record> {
+        self.records().find(|r| r.name() == name)
     }
 
     pub fn get_version(&self) -> u16 {
@@ -59,42 +267,196 @@ impl Disassembler {
         (&self.program_name, &self.network)
     }
 
+    // Imported program ids as (name, network)
+    pub fn imports(&self) -> &[(String, String)] {
+        &self.imports
+    }
+
+    // Declarations callers of the program depend on: mappings, records, structs and the
+    // inputs and outputs of functions, in Aleo instructions syntax
+    pub fn interface(&self) -> String {
+        let mut o = self.import_lines();
+        o.write_fmt(format_args!("program {}.{};\n", self.program_name, self.network)).unwrap();
+        for component in self.components.iter() {
+            let declaration = match component {
+                Component::Function(f) => match f.function_type() {
+                    FunctionType::Function => f.interface(),
+                    _ => continue,
+                },
+                c => c.assembly(),
+            };
+            o.write_fmt(format_args!("\n{}\n", declaration)).unwrap();
+        }
+        o
+    }
+
+    // Callable interface as JSON, for front-ends building transactions against the program:
+    // `program`, `version`, `imports`, then `functions`, `records`, `structs` and `mappings`
+    // in declaration order. Closures are internal and left out.
+    pub fn abi(&self) -> String {
+        let imports = self.imports.iter().map(|(p, n)| format!("{}.{}", p, n).into()).collect();
+        let functions = self.functions()
+            .filter(|f| f.function_type() == FunctionType::Function)
+            .map(|f| f.abi())
+            .collect();
+        Json::Object(vec![
+            ("program", format!("{}.{}", self.program_name, self.network).into()),
+            ("version", Json::Number(self.version as u64)),
+            ("imports", Json::Array(imports)),
+            ("functions", Json::Array(functions)),
+            ("records", Json::Array(self.records().map(|r| r.abi()).collect())),
+            ("structs", Json::Array(self.structs().map(|s| s.abi()).collect())),
+            ("mappings", Json::Array(self.mappings().map(|m| m.abi()).collect())),
+        ]).pretty()
+    }
+
+    pub fn disassemble(&mut self) -> Result<(), DecodeError> {
+        // The cursor borrows the program bytes while the components are filled in, and the
+        // components of an earlier call are dropped rather than decoded a second time
+        let buf = core::mem::take(&mut self.bytes);
+        *self = Self::default();
+        // Nothing in the bytecode says which numbering its finalize commands use, programs
+        // deployed before snarkVM renumbered them only decode with the legacy one. Only a
+        // command the current numbering does not decode is retried with it.
+        let result = self.read(&buf, CommandLayout::Current).or_else(|e| match e {
+            DecodeError::UnknownCommandTag(..) | DecodeError::Unsupported(..) => {
+                let mut legacy = Self::default();
+                legacy.read(&buf, CommandLayout::Legacy).map_err(|_| e)?;
+                *self = legacy;
+                Ok(())
+            },
+            e => Err(e),
+        });
+        self.bytes = buf;
+        result?;
+        self.check_imports();
+        Ok(())
+    }
 
-    pub fn disassemble(&mut self) {
-        self.read_header();
-        self.read_num_components();
-        self.read_components();
+    fn read(&mut self, buf: &[u8], layout: CommandLayout) -> Result<(), DecodeError> {
+        let mut bytes = ByteCode::new(buf);
+        bytes.set_command_layout(layout);
+        self.read_header(&mut bytes)
+            .and_then(|_| self.read_num_components(&mut bytes))
+            .and_then(|_| self.read_components(&mut bytes))
     }
 
 }
 
-impl Assembly for Disassembler {
-    fn assembly(&self) -> String {
+impl Disassembler {
+    // Import lines shared by both outputs, followed by a blank line
+    fn import_lines(&self) -> String {
         let mut o = String::new();
-        o.write_fmt(format_args!("program {}.{}\n\n", self.program_name, self.network)).unwrap();
-        let mappings = self.mappings.iter().map(|m| format!("{}", m.assembly())).collect::<Vec<String>>().join("\n\n");
-        let functions = self.functions.iter().map(|f| format!("{}", f.assembly())).collect::<Vec<String>>().join("\n\n");
-        let structs = self.structs.iter().map(|s| format!("{}", s.assembly())).collect::<Vec<String>>().join("\n\n");
-        let records = self.records.iter().map(|s| format!("{}", s.assembly())).collect::<Vec<String>>().join("\n\n");
-        o.write_fmt(format_args!("{}\n", mappings)).unwrap();
-        o.write_fmt(format_args!("{}\n", records)).unwrap();
-        o.write_fmt(format_args!("{}\n", structs)).unwrap();
-        o.write_fmt(format_args!("{}\n", functions)).unwrap();
+        for (program, network) in self.imports.iter() {
+            o.write_fmt(format_args!("import {}.{};\n", program, network)).unwrap();
+        }
+        if !self.imports.is_empty() {
+            o.write_str("\n").unwrap();
+        }
         o
     }
-    
-    fn leo(&self) -> String {
-        let mut o = String::new();
-        o.write_fmt(format_args!("program {}.{} {{\n\n", self.program_name, self.network)).unwrap();
-        let mappings = self.mappings.iter().map(|m| format!("{}", m.leo())).collect::<Vec<String>>().join("\n\n");
-        let functions = self.functions.iter().map(|f| format!("{}", f.leo())).collect::<Vec<String>>().join("\n\n");
-        let structs = self.structs.iter().map(|s| format!("{}", s.leo())).collect::<Vec<String>>().join("\n\n");
-        let records = self.records.iter().map(|s| format!("{}", s.leo())).collect::<Vec<String>>().join("\n\n");
-        o.write_fmt(format_args!("{}\n", mappings)).unwrap();
-        o.write_fmt(format_args!("{}\n", records)).unwrap();
-        o.write_fmt(format_args!("{}\n", structs)).unwrap();
-        o.write_fmt(format_args!("{}\n", functions)).unwrap();
-        o.write_fmt(format_args!("}}\n")).unwrap();
+}
+
+impl Disassembler {
+    // Aleo instructions with the inferred type of every register an instruction or command
+    // writes, e.g. `add r0 r1 into r2; // r2: u64`. Still valid source, the types are comments.
+    pub fn annotated_assembly(&self) -> String {
+        self.render(Some(&Infer::new(self)))
+    }
+
+    // Leo source like `leo()`, with the optional restructuring chosen by `options`
+    pub fn leo_with(&self, options: LeoOptions) -> String {
+        crate::leo::decompile_with(self, options)
+    }
+
+    fn render(&self, infer: Option<&Infer>) -> String {
+        let mut o = self.import_lines();
+        // Same layout as snarkVM prints: components in declaration order, separated by a blank line
+        o.write_fmt(format_args!("program {}.{};\n\n", self.program_name, self.network)).unwrap();
+        let components = self.components.iter()
+            .map(|c| match (c, infer) {
+                (Component::Function(f), Some(_)) => f.render(infer),
+                _ => c.assembly(),
+            })
+            .collect::<Vec<String>>();
+        if !components.is_empty() {
+            o.write_fmt(format_args!("{}\n", components.join("\n\n"))).unwrap();
+        }
         o
     }
-}
\ No newline at end of file
+}
+
+impl Assembly for Disassembler {
+    fn assembly(&self) -> String {
+        self.render(None)
+    }
+
+    fn leo(&self) -> String {
+        crate::leo::decompile(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::{println, time::Instant};
+    use alloc::vec::Vec;
+    use super::Disassembler;
+
+    // Program with one function made of `instructions` copies of `add r0 r1 into r2`
+    fn synthetic_program(instructions: u32) -> Vec<u8> {
+        let mut b = vec![0, 0, 5];
+        b.extend_from_slice(b"bench");
+        b.push(4);
+        b.extend_from_slice(b"aleo");
+        // no imports, one component
+        b.extend_from_slice(&[0, 1, 0]);
+        // function `f` with inputs `r0` and `r1`
+        b.extend_from_slice(&[4, 1, b'f', 2, 0]);
+        b.extend_from_slice(&[0, 0, 1, 0, 0x0b, 0]);
+        b.extend_from_slice(&[0, 1, 2, 0, 0x0b, 0]);
+        b.extend_from_slice(&instructions.to_le_bytes());
+        for _ in 0..instructions {
+            b.extend_from_slice(&[2, 0, 1, 0, 0, 1, 0, 1, 0, 2]);
+        }
+        // output `r2`, no finalize
+        b.extend_from_slice(&[1, 0, 0, 2, 2, 0, 0x0b, 0, 0]);
+        b
+    }
+
+    #[test]
+    fn decodes_large_program() {
+        let mut a = Disassembler::from_bytes(synthetic_program(100_000));
+        a.disassemble().unwrap();
+        assert_eq!(a.functions().next().unwrap().instructions().len(), 100_000);
+    }
+
+    #[test]
+    fn disassembles_twice() {
+        let mut a = Disassembler::from_bytes(synthetic_program(3));
+        a.disassemble().unwrap();
+        a.disassemble().unwrap();
+        assert_eq!(a.functions().count(), 1);
+        assert_eq!(a.components().len(), 1);
+        assert_eq!(a.to_bytes(), Ok(synthetic_program(3)));
+    }
+
+    // cargo test --release -- --ignored --nocapture bench_decoding
+    #[test]
+    #[ignore]
+    fn bench_decoding() {
+        let mut per_byte = Vec::new();
+        for instructions in [100_000, 200_000, 400_000, 800_000] {
+            let program = synthetic_program(instructions);
+            let len = program.len();
+            let mut a = Disassembler::from_bytes(program);
+            let start = Instant::now();
+            a.disassemble().unwrap();
+            let elapsed = start.elapsed();
+            println!("{:>9} bytes decoded in {:?} ({:.1} ns/byte)", len, elapsed, elapsed.as_nanos() as f64 / len as f64);
+            per_byte.push(elapsed.as_nanos() as f64 / len as f64);
+        }
+        // Quadratic decoding would be ~8x slower per byte on the largest input
+        assert!(per_byte[3] < per_byte[0] * 3.0);
+    }
+}
@@ -0,0 +1,181 @@
+use core::fmt;
+use alloc::string::String;
+
+// Where in the bytecode a decoding error happened
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    // Byte offset from the start of the program
+    pub offset: usize,
+    // Name of the enclosing component or function, empty at program level
+    pub context: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.context.is_empty() {
+            write!(f, "at offset {}", self.offset)
+        } else {
+            write!(f, "at offset {} in `{}`", self.offset, self.context)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // The bytecode ended in the middle of a value
+    UnexpectedEof(Location),
+    // Instruction opcode outside of the known instruction set
+    UnknownOpcode(u16, Location),
+    // Literal type id outside of the known literal types
+    UnknownLiteralType(u16, Location),
+    // Tag of a plaintext, register or value type is not recognised
+    UnknownTypeTag(u8, Location),
+    // Visibility / attribute byte is not recognised
+    UnknownAttribute(u8, Location),
+    // Operand variant is not recognised
+    UnknownOperandTag(u8, Location),
+    // Register variant is not recognised
+    UnknownRegisterTag(u8, Location),
+    // Program component tag is not recognised
+    UnknownComponentTag(u8, Location),
+    // Finalize command tag is not recognised
+    UnknownCommandTag(u8, Location),
+    // A presence flag is neither 0 nor 1
+    InvalidFlag(u8, Location),
+    // Callee of a call is neither internal nor external
+    UnknownCallVariant(u8, Location),
+    // A boolean literal is neither 0 nor 1
+    InvalidBoolean(u8, Location),
+    // A finalize command refers to a mapping the program does not declare
+    UnknownMapping(String, Location),
+    // An identifier is not valid UTF-8
+    InvalidUtf8Identifier(Location),
+    // A string literal is not valid UTF-8
+    InvalidUtf8String(Location),
+    // An instruction declares an operand count it cannot have
+    InvalidOperandCount(u8, Location),
+    // A well-formed construct this decoder does not handle yet
+    Unsupported(&'static str, Location),
+}
+
+impl DecodeError {
+    pub fn location(&self) -> &Location {
+        match self {
+            Self::UnexpectedEof(l)
+            | Self::UnknownOpcode(_, l)
+            | Self::UnknownLiteralType(_, l)
+            | Self::UnknownTypeTag(_, l)
+            | Self::UnknownAttribute(_, l)
+            | Self::UnknownOperandTag(_, l)
+            | Self::UnknownRegisterTag(_, l)
+            | Self::UnknownComponentTag(_, l)
+            | Self::UnknownCommandTag(_, l)
+            | Self::InvalidFlag(_, l)
+            | Self::UnknownCallVariant(_, l)
+            | Self::InvalidBoolean(_, l)
+            | Self::UnknownMapping(_, l)
+            | Self::InvalidUtf8Identifier(l)
+            | Self::InvalidUtf8String(l)
+            | Self::InvalidOperandCount(_, l)
+            | Self::Unsupported(_, l) => l,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.location().offset
+    }
+
+    pub fn context(&self) -> &str {
+        &self.location().context
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof(l) => write!(f, "unexpected end of bytecode {}", l),
+            Self::UnknownOpcode(o, l) => write!(f, "unknown opcode {} {}", o, l),
+            Self::UnknownLiteralType(t, l) => write!(f, "unknown literal type {} {}", t, l),
+            Self::UnknownTypeTag(t, l) => write!(f, "unknown type tag {} {}", t, l),
+            Self::UnknownAttribute(a, l) => write!(f, "unknown attribute {} {}", a, l),
+            Self::UnknownOperandTag(t, l) => write!(f, "unknown operand tag {} {}", t, l),
+            Self::UnknownRegisterTag(t, l) => write!(f, "unknown register tag {} {}", t, l),
+            Self::UnknownComponentTag(t, l) => write!(f, "unknown component tag {} {}", t, l),
+            Self::UnknownCommandTag(t, l) => write!(f, "unknown command tag {} {}", t, l),
+            Self::InvalidFlag(v, l) => write!(f, "invalid flag {} {}", v, l),
+            Self::UnknownCallVariant(v, l) => write!(f, "unknown call variant {} {}", v, l),
+            Self::InvalidBoolean(v, l) => write!(f, "invalid boolean {} {}", v, l),
+            Self::UnknownMapping(m, l) => write!(f, "unknown mapping `{}` {}", m, l),
+            Self::InvalidUtf8Identifier(l) => write!(f, "identifier is not valid utf-8 {}", l),
+            Self::InvalidUtf8String(l) => write!(f, "string literal is not valid utf-8 {}", l),
+            Self::InvalidOperandCount(n, l) => write!(f, "invalid operand count {} {}", n, l),
+            Self::Unsupported(what, l) => write!(f, "unsupported {} {}", what, l),
+        }
+    }
+}
+
+// Part of a program the bytecode format cannot represent, e.g. after editing it through the
+// mutable API or loading it with serde
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    // An identifier longer than the 255 bytes its length prefix can hold
+    IdentifierTooLong(String),
+    // More items than their count can hold, e.g. more than 255 call inputs
+    TooMany(&'static str, usize),
+    // A record type where only plaintext types are allowed, e.g. a struct member
+    NotPlaintext(String),
+    // A register type whose visibility does not match it, e.g. a record type marked public
+    MismatchedAttribute(String, String),
+    // An address, field, group or scalar literal that is not 32 bytes long
+    InvalidLiteralLength(usize),
+    // A string literal longer than 65535 bytes
+    StringTooLong(usize),
+    // A program id operand holding a function or record locator
+    InvalidProgramId(String),
+    // A function component that is neither a function nor a closure
+    InvalidFunctionType(String),
+    // An instruction whose operands or output do not match its opcode
+    InvalidInstruction(String),
+    // A finalize command the layout of its block has no tag for, e.g. `increment` next to `remove`
+    UnsupportedCommand(String),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IdentifierTooLong(i) => write!(f, "identifier `{}` is longer than 255 bytes", i),
+            Self::TooMany(what, n) => write!(f, "too many {} ({})", what, n),
+            Self::NotPlaintext(t) => write!(f, "`{}` is not a plaintext type", t),
+            Self::MismatchedAttribute(t, a) => write!(f, "type `{}` with visibility `{}`", t, a),
+            Self::InvalidLiteralLength(n) => write!(f, "literal of {} bytes, expected 32", n),
+            Self::StringTooLong(n) => write!(f, "string literal of {} bytes is longer than 65535", n),
+            Self::InvalidProgramId(p) => write!(f, "`{}` is not a program id", p),
+            Self::InvalidFunctionType(name) => write!(f, "`{}` is neither a function nor a closure", name),
+            Self::InvalidInstruction(i) => write!(f, "malformed instruction `{}`", i),
+            Self::UnsupportedCommand(c) => write!(f, "command `{}` cannot be encoded in the layout of its finalize block", c),
+        }
+    }
+}
+
+// Failure to parse Aleo instructions source, at the 1-based line and column of the offending token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
@@ -0,0 +1,226 @@
+// Types of the registers of a function or finalize block. Registers are assigned once, before
+// they are read, so a single pass in program order sees the type of every operand it needs:
+// inputs give theirs, instructions derive theirs from their operands and opcode.
+use crate::Disassembler;
+use crate::components::Function;
+use crate::components::commands::Command;
+use crate::components::finalize::Finalize;
+use crate::components::instructions::{Instruction, Locator, Opcode, Operand, Output};
+use crate::components::registers::{IoRegister, Register};
+use crate::components::types::Type;
+use crate::output::Assembly;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Inferred types by register locator, registers whose type could not be found are missing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegisterTypes {
+    types: BTreeMap<usize, Type>,
+}
+
+impl RegisterTypes {
+    pub fn get(&self, locator: usize) -> Option<&Type> {
+        self.types.get(&locator)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Type)> {
+        self.types.iter().map(|(l, t)| (*l, t))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn set(&mut self, register: &Register, value_type: Option<Type>) {
+        // Member accesses only read, a destination is always a plain register
+        if let (true, Some(value_type)) = (register.identifiers().is_empty(), value_type) {
+            self.types.insert(register.locator(), value_type);
+        }
+    }
+}
+
+// ` // r3: u64` comment listing the known types of `registers`, empty if there are none
+pub(crate) fn annotation(types: &RegisterTypes, registers: &[&Register]) -> String {
+    let known = registers.iter()
+        .filter_map(|r| types.get(r.locator()).map(|t| format!("r{}: {}", r.locator(), t.assembly())))
+        .collect::<Vec<String>>();
+    match known.is_empty() {
+        true => String::new(),
+        false => format!(" // {}", known.join(", ")),
+    }
+}
+
+#[derive(Default)]
+pub struct Infer<'a> {
+    // Declarations of the program, for member accesses, calls and mappings
+    program: Option<&'a Disassembler>,
+}
+
+impl<'a> Infer<'a> {
+    pub fn new(program: &'a Disassembler) -> Self {
+        Self { program: Some(program) }
+    }
+
+    fn inputs(&self, inputs: &[IoRegister]) -> RegisterTypes {
+        let mut types = RegisterTypes::default();
+        inputs.iter().for_each(|i| types.set(i.register(), Some(i.value_type().clone())));
+        types
+    }
+
+    pub fn function(&self, function: &Function) -> RegisterTypes {
+        let mut types = self.inputs(function.inputs());
+        for instruction in function.instructions() {
+            self.instruction(&mut types, instruction);
+        }
+        types
+    }
+
+    pub fn finalize(&self, finalize: &Finalize) -> RegisterTypes {
+        let mut types = self.inputs(finalize.inputs());
+        for command in finalize.commands() {
+            let mapping = |name: &str| self.program.and_then(|p| p.mapping(name)).map(|m| m.value().value_type().clone());
+            match command {
+                Command::Instruction(i) => self.instruction(&mut types, i),
+                Command::Contains { destination, .. } => types.set(destination, Some(Type::Boolean)),
+                Command::Get { mapping: m, destination, .. } => types.set(destination, mapping(m)),
+                Command::GetOrUse { mapping: m, default, destination, .. } => {
+                    let value_type = mapping(m).or_else(|| self.operand(&types, default));
+                    types.set(destination, value_type);
+                },
+                Command::Decrement { .. } | Command::Increment { .. } | Command::Remove { .. } | Command::Set { .. } => {},
+            }
+        }
+        types
+    }
+
+    // Type of `name` in a struct or record of type `value_type`
+    fn member(&self, value_type: &Type, name: &str) -> Option<Type> {
+        match (value_type, name) {
+            (Type::Record(_) | Type::ExternalRecord(_), "owner") => Some(Type::Address),
+            (Type::Record(_) | Type::ExternalRecord(_), "gates") => Some(Type::U64),
+            (Type::Record(record), _) => self.program?.record(record)?.entries().iter().find(|e| e.name() == name).map(|e| e.value_type().clone()),
+            (Type::Other(structure), _) => self.program?.structure(structure)?.member(name).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn register(&self, types: &RegisterTypes, register: &Register) -> Option<Type> {
+        let base = types.get(register.locator())?.clone();
+        register.identifiers().iter().try_fold(base, |value_type, name| self.member(&value_type, name))
+    }
+
+    pub fn operand(&self, types: &RegisterTypes, operand: &Operand) -> Option<Type> {
+        match operand {
+            Operand::Literal(l) => Some(l.literal_type()),
+            Operand::Register(r) => self.register(types, r),
+            Operand::Caller => Some(Type::Address),
+            Operand::ProgramId(_) => None,
+        }
+    }
+
+    // Output types of an internal function or closure
+    fn signature(&self, callee: &Locator) -> Option<Vec<Type>> {
+        match callee {
+            Locator::Internal(name) => Some(self.program?.function(name)?.outputs().iter().map(|o| o.value_type().clone()).collect()),
+            Locator::External(_) | Locator::Program(_) => None,
+        }
+    }
+
+    fn instruction(&self, types: &mut RegisterTypes, instruction: &Instruction) {
+        let operand = |n: usize| instruction.operands().get(n).and_then(|o| self.operand(types, o));
+        // Arithmetic keeps the type of its operands, whichever is known
+        let either = || operand(0).or_else(|| operand(1));
+        let value_type = match instruction.opcode() {
+            Opcode::Call => {
+                let outputs = instruction.callee().and_then(|c| self.signature(c)).unwrap_or_default();
+                for (n, register) in instruction.destinations().into_iter().enumerate() {
+                    types.set(register, outputs.get(n).cloned());
+                }
+                return;
+            },
+            Opcode::Cast => match instruction.output() {
+                Output::Cast((_, value_type)) => Some(value_type.clone()),
+                _ => None,
+            },
+            Opcode::AssertEq | Opcode::AssertNeq => return,
+            Opcode::GreaterThan | Opcode::GreaterThanOrEqual | Opcode::LessThan | Opcode::LessThanOrEqual
+            | Opcode::IsEq | Opcode::IsNeq => Some(Type::Boolean),
+            Opcode::HashBHP256 | Opcode::HashBHP512 | Opcode::HashBHP768 | Opcode::HashBHP1024
+            | Opcode::HashPED64 | Opcode::HashPED128 | Opcode::HashPSD2 | Opcode::HashPSD4 | Opcode::HashPSD8
+            | Opcode::CommitBHP256 | Opcode::CommitBHP512 | Opcode::CommitBHP768 | Opcode::CommitBHP1024 => Some(Type::Field),
+            Opcode::CommitPED64 | Opcode::CommitPED128 => Some(Type::Group),
+            // A group scaled by a scalar, in either order, stays a group
+            Opcode::Mul if operand(0) == Some(Type::Group) || operand(1) == Some(Type::Group) => Some(Type::Group),
+            // The exponent or shift amount has its own type
+            Opcode::Pow | Opcode::PowWrapped | Opcode::Shl | Opcode::ShlWrapped | Opcode::Shr | Opcode::ShrWrapped => operand(0),
+            Opcode::Ternary => operand(1).or_else(|| operand(2)),
+            Opcode::Abs | Opcode::AbsWrapped | Opcode::Double | Opcode::Inv | Opcode::Neg | Opcode::Not
+            | Opcode::Square | Opcode::SquareRoot => operand(0),
+            Opcode::Add | Opcode::AddWrapped | Opcode::Sub | Opcode::SubWrapped | Opcode::Mul | Opcode::MulWrapped
+            | Opcode::Div | Opcode::DivWrapped | Opcode::Rem | Opcode::RemWrapped | Opcode::Mod
+            | Opcode::And | Opcode::Or | Opcode::Xor | Opcode::Nand | Opcode::Nor => either(),
+        };
+        if let Output::Single(register) | Output::Cast((register, _)) = instruction.output() {
+            types.set(register, value_type);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_through_members_calls_and_mappings() {
+        let source = "program test.aleo;
+
+mapping account:
+    key owner as address.public;
+    value amount as u64.public;
+
+struct point:
+    x as field;
+    y as i8;
+
+closure helper:
+    input r0 as point;
+    is.eq r0.y 0i8 into r1;
+    output r1 as boolean;
+
+function f:
+    input r0 as point.private;
+    add r0.x 1field into r1;
+    neg r0.y into r2;
+    call helper r0 into r3;
+    ternary r3 r2 5i8 into r4;
+    cast r1 r4 into r5 as point;
+    hash.psd2 r2 into r6;
+    output r5 as point.private;
+    finalize self.caller;
+
+finalize f:
+    input r0 as address.public;
+    get.or_use account[r0] 0u64 into r1;
+    contains account[r0] into r2;
+    add r1 r1 into r3;
+";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let f = a.function("f").unwrap();
+        let infer = Infer::new(&a);
+        let types = infer.function(f);
+        let expected = [Type::Other("point".into()), Type::Field, Type::I8, Type::Boolean, Type::I8, Type::Other("point".into()), Type::Field];
+        assert_eq!(types.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>(), expected);
+        let types = infer.finalize(f.finalize().unwrap());
+        assert_eq!(types.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>(), [Type::Address, Type::U64, Type::Boolean, Type::U64]);
+
+        let annotated = a.annotated_assembly();
+        assert!(annotated.contains("    add r0.x 1field into r1; // r1: field\n"));
+        assert!(annotated.contains("    get.or_use account[r0] 0u64 into r1; // r1: u64\n"));
+        assert_eq!(Disassembler::from_aleo(&annotated).expect("malformed annotated source").assembly(), a.assembly());
+    }
+}
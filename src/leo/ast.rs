@@ -0,0 +1,249 @@
+// Leo syntax tree the decoded program is lowered into before printing. It only models what the
+// decompiler emits, so rewrites of the output work on structure instead of strings.
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    // Imported program ids, e.g. `credits.aleo`
+    pub imports: Vec<String>,
+    // Program id, e.g. `test.aleo`
+    pub id: String,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Record(Composite),
+    Struct(Composite),
+    Mapping(Mapping),
+    Function(Function),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Constant,
+    Public,
+    Private,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    // Primitive, struct or record type by name, e.g. `u64`, `point`, `credits.aleo/credits`
+    Named(String),
+    Tuple(Vec<Type>),
+}
+
+// Member of a struct or record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    // Only record members carry one
+    pub visibility: Option<Visibility>,
+}
+
+// Struct or record declaration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composite {
+    pub name: String,
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub name: String,
+    pub key: Type,
+    pub value: Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    Transition,
+    // Lowered from a closure
+    Function,
+    Finalize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    pub visibility: Option<Visibility>,
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub visibility: Option<Visibility>,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub kind: FunctionKind,
+    pub name: String,
+    pub inputs: Vec<Parameter>,
+    pub outputs: Vec<Output>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    // `let r2: u64 = r0 + r1;`, or `(r2, r3) = f(r0);` with several targets and no known type
+    Assign { targets: Vec<String>, ty: Option<Type>, value: Expression },
+    // Expression evaluated for its effect, e.g. `assert_eq(r0, r1);` or a mapping update
+    Expression(Expression),
+    // `return (r2, r3) then finalize(r0);`, `finalize` is set for transitions with a finalize block
+    Return { values: Vec<Expression>, finalize: Option<Vec<Expression>> },
+    // `let r9: u64;`, a register assigned in both branches of the following `if`
+    Declare { name: String, ty: Option<Type> },
+    // `if r2 { ... } else { ... }`, recovered from ternaries selecting between branch results
+    If { condition: Expression, then: Vec<Statement>, otherwise: Vec<Statement> },
+    // `for i: u32 in 0u32..4u32 { ... }`, re-rolled from the unrolled iterations
+    For { variable: String, ty: Type, start: Expression, end: Expression, body: Vec<Statement> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    // Register, mapping or `self.caller`
+    Identifier(String),
+    // Literal as written in Leo, e.g. `1u32` or `-5i8`
+    Literal(String),
+    // `inner.name`
+    Member { inner: Box<Expression>, name: String },
+    Unary { op: UnaryOp, operand: Box<Expression> },
+    Binary { op: BinaryOp, left: Box<Expression>, right: Box<Expression> },
+    Ternary { condition: Box<Expression>, if_true: Box<Expression>, if_false: Box<Expression> },
+    // `receiver.name(arguments)`, e.g. `r0.add_wrapped(r1)`
+    Method { receiver: Box<Expression>, name: String, arguments: Vec<Expression> },
+    // Function, closure or built-in call, e.g. `helper(r0)`, `BHP256::hash(r0)` or `assert_eq(r0, r1)`
+    Call { function: String, arguments: Vec<Expression> },
+    // Struct or record construction, members are positional when the declaration is unknown
+    Composite { name: String, members: Vec<(Option<String>, Expression)> },
+}
+
+impl Expression {
+    // Number of times the identifier `name` is read
+    pub fn uses(&self, name: &str) -> usize {
+        match self {
+            Self::Identifier(n) if n == name => 1,
+            _ => self.operands().into_iter().map(|o| o.uses(name)).sum(),
+        }
+    }
+
+    // Direct subexpressions, in evaluation order
+    pub fn operands(&self) -> Vec<&Expression> {
+        match self {
+            Self::Identifier(_) | Self::Literal(_) => vec![],
+            Self::Member { inner, .. } => vec![inner],
+            Self::Unary { operand, .. } => vec![operand],
+            Self::Binary { left, right, .. } => vec![left, right],
+            Self::Ternary { condition, if_true, if_false } => vec![condition, if_true, if_false],
+            Self::Method { receiver, arguments, .. } => core::iter::once(&**receiver).chain(arguments.iter()).collect(),
+            Self::Call { arguments, .. } => arguments.iter().collect(),
+            Self::Composite { members, .. } => members.iter().map(|(_, e)| e).collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Identifier(_) | Self::Literal(_) => vec![],
+            Self::Member { inner, .. } => vec![inner],
+            Self::Unary { operand, .. } => vec![operand],
+            Self::Binary { left, right, .. } => vec![left, right],
+            Self::Ternary { condition, if_true, if_false } => vec![condition, if_true, if_false],
+            Self::Method { receiver, arguments, .. } => core::iter::once(&mut **receiver).chain(arguments.iter_mut()).collect(),
+            Self::Call { arguments, .. } => arguments.iter_mut().collect(),
+            Self::Composite { members, .. } => members.iter_mut().map(|(_, e)| e).collect(),
+        }
+    }
+}
+
+impl Statement {
+    // Expressions of the statement, in evaluation order, without those of nested blocks
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Self::Assign { value, .. } | Self::Expression(value) | Self::If { condition: value, .. } => vec![value],
+            Self::Return { values, finalize } => values.iter().chain(finalize.iter().flatten()).collect(),
+            Self::For { start, end, .. } => vec![start, end],
+            Self::Declare { .. } => vec![],
+        }
+    }
+
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Assign { value, .. } | Self::Expression(value) | Self::If { condition: value, .. } => vec![value],
+            Self::Return { values, finalize } => values.iter_mut().chain(finalize.iter_mut().flatten()).collect(),
+            Self::For { start, end, .. } => vec![start, end],
+            Self::Declare { .. } => vec![],
+        }
+    }
+
+    // Statements of the nested blocks
+    pub fn nested(&self) -> Vec<&Statement> {
+        match self {
+            Self::If { then, otherwise, .. } => then.iter().chain(otherwise.iter()).collect(),
+            Self::For { body, .. } => body.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    // Number of times the identifier `name` is read, nested blocks included
+    pub fn uses(&self, name: &str) -> usize {
+        let nested = self.nested().into_iter().map(|s| s.uses(name)).sum::<usize>();
+        nested + self.expressions().into_iter().map(|e| e.uses(name)).sum::<usize>()
+    }
+
+    // Names assigned or declared, nested blocks included
+    pub fn assigned(&self) -> Vec<&String> {
+        let mut names = match self {
+            Self::Assign { targets, .. } => targets.iter().collect(),
+            Self::Declare { name, .. } => vec![name],
+            _ => vec![],
+        };
+        names.extend(self.nested().into_iter().flat_map(|s| s.assigned()));
+        names
+    }
+}
+
+// Names assigned more than once. Lowered registers are assigned once, variables declared ahead of
+// an `if` or carried across loop iterations are assigned again.
+pub fn reassigned(statements: &[Statement]) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut again = BTreeSet::new();
+    for name in statements.iter().flat_map(|s| s.assigned()) {
+        if !seen.insert(name) {
+            again.insert(name.clone());
+        }
+    }
+    again
+}
@@ -0,0 +1,110 @@
+// Recovery of `if`/`else` from ternaries. Leo evaluates both branches of an `if` and selects every
+// register they assign with a `ternary` on its condition, so the statements only computing the
+// true operands of the ternaries sharing a condition form the `then` branch, and those only
+// computing the false operands the `else` branch. Statements with side effects are never moved
+// into a branch, that would make them conditional.
+use super::ast::{Expression, Function, Statement};
+use super::inline::statement_has_effects;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn function(f: &mut Function) {
+    super::blocks(f, block);
+}
+
+// Variables assigned more than once, e.g. carried across loop iterations, are left in place
+fn block(body: &mut Vec<Statement>, finalize: bool, reassigned: &BTreeSet<String>) {
+    // A recovered `if` replaces the ternaries on its condition, so the conditions left to try only shrink
+    while let Some(structured) = conditions(body, reassigned).iter().find_map(|c| structure(body, c, finalize, reassigned)) {
+        *body = structured;
+    }
+}
+
+// `target = condition ? if_true : if_false`
+fn ternary(s: &Statement) -> Option<(&String, &Expression, [&Expression; 2])> {
+    match s {
+        Statement::Assign { targets, value: Expression::Ternary { condition, if_true, if_false }, .. } if targets.len() == 1 => {
+            Some((&targets[0], condition, [if_true, if_false]))
+        },
+        _ => None,
+    }
+}
+
+// Conditions of the ternaries of `body`, in order of first use
+fn conditions(body: &[Statement], reassigned: &BTreeSet<String>) -> Vec<Expression> {
+    let mut conditions = Vec::new();
+    for (_, condition, _) in body.iter().filter_map(ternary).filter(|(target, ..)| !reassigned.contains(*target)) {
+        if !conditions.contains(condition) {
+            conditions.push(condition.clone());
+        }
+    }
+    conditions
+}
+
+// Statements whose result is only read by the `operand` (0 true, 1 false) of the ternaries at
+// `selects`, directly or through other such statements
+fn branch(body: &[Statement], selects: &[usize], operand: usize, finalize: bool, reassigned: &BTreeSet<String>) -> BTreeSet<usize> {
+    let mut slice = BTreeSet::new();
+    let last = selects.last().copied().unwrap_or_default();
+    // Registers are read after they are assigned, so going backwards sees every reader first
+    for i in (0..last).rev() {
+        let name = match &body[i] {
+            Statement::Assign { targets, .. } if targets.len() == 1 && !selects.contains(&i) && !reassigned.contains(&targets[0])
+                && !statement_has_effects(&body[i], finalize) => &targets[0],
+            _ => continue,
+        };
+        let mut used = false;
+        let only_branch = body.iter().enumerate().skip(i + 1).all(|(j, s)| {
+            let count = s.uses(name);
+            used |= count > 0;
+            count == 0 || slice.contains(&j) || (selects.contains(&j) && ternary(s).map(|(_, _, operands)| operands[operand].uses(name)) == Some(count))
+        });
+        if used && only_branch {
+            slice.insert(i);
+        }
+    }
+    slice
+}
+
+// `body` with the ternaries on `condition` turned into an `if`, placed where the last of them was
+fn structure(body: &[Statement], condition: &Expression, finalize: bool, reassigned: &BTreeSet<String>) -> Option<Vec<Statement>> {
+    let selects = (0..body.len())
+        .filter(|i| ternary(&body[*i]).is_some_and(|(target, c, _)| c == condition && !reassigned.contains(target)))
+        .collect::<Vec<usize>>();
+    let (then, otherwise) = (branch(body, &selects, 0, finalize, reassigned), branch(body, &selects, 1, finalize, reassigned));
+    // Ternaries between values computed anyway read better as they are
+    if then.is_empty() && otherwise.is_empty() {
+        return None;
+    }
+    // What stays before the `if` or moves into it can't read what the `if` assigns
+    let (first, last) = (*selects.first()?, *selects.last()?);
+    let results = selects.iter().filter_map(|i| ternary(&body[*i])).map(|(target, _, _)| target).collect::<Vec<_>>();
+    if (first..last).any(|i| !selects.contains(&i) && results.iter().any(|r| body[i].uses(r) > 0)) {
+        return None;
+    }
+    let (mut structured, mut declarations) = (Vec::new(), Vec::new());
+    let (mut then_block, mut else_block) = (Vec::new(), Vec::new());
+    let assign = |target: &String, value: &Expression| Statement::Assign { targets: vec![target.clone()], ty: None, value: value.clone() };
+    for (i, s) in body.iter().enumerate() {
+        match (ternary(s), s) {
+            _ if then.contains(&i) => then_block.push(s.clone()),
+            _ if otherwise.contains(&i) => else_block.push(s.clone()),
+            (Some((target, _, [if_true, if_false])), Statement::Assign { ty, .. }) if selects.contains(&i) => {
+                declarations.push(Statement::Declare { name: target.clone(), ty: ty.clone() });
+                then_block.push(assign(target, if_true));
+                else_block.push(assign(target, if_false));
+                if i == last {
+                    structured.append(&mut declarations);
+                    structured.push(Statement::If {
+                        condition: condition.clone(),
+                        then: core::mem::take(&mut then_block),
+                        otherwise: core::mem::take(&mut else_block),
+                    });
+                }
+            },
+            _ => structured.push(s.clone()),
+        }
+    }
+    Some(structured)
+}
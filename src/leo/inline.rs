@@ -0,0 +1,252 @@
+// Folding of registers used exactly once into the expression using them, so `a + b * c` reads as
+// one expression instead of a temporary per instruction. The printer adds the parentheses the
+// nesting needs. Side effects (asserts, calls, mapping accesses, hashes in finalize) keep their
+// order: an expression with one is only moved past side-effect free statements, and only to
+// where it is evaluated before any other side effect of its new statement. Expressions stop
+// growing at `MAX_DEPTH`, past it a chain of single uses is cut by a `let`.
+use super::ast::{Expression, Function, Statement};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Nesting of the deepest expression folding builds, far from what the recursive walkers of the
+// printer and the passes can take
+const MAX_DEPTH: usize = 64;
+
+pub fn function(f: &mut Function) {
+    super::blocks(f, block);
+}
+
+// Reads and definitions of a block, nested blocks included, computed once and kept up to date
+// as definitions are folded, so each candidate is checked without scanning the rest of the block.
+// Folded values are substituted at the end, a statement refers to them by name until then.
+#[derive(Default)]
+struct Block {
+    // Number of reads of each variable
+    uses: BTreeMap<String, usize>,
+    // Number of reads in the expressions of the statements themselves, outside nested blocks
+    direct: BTreeMap<String, usize>,
+    // Statement of the first read
+    first: BTreeMap<String, usize>,
+    // Statements assigning each variable
+    assigned: BTreeMap<String, BTreeSet<usize>>,
+    // Statements with side effects, those of the values folded into them included
+    effects: BTreeSet<usize>,
+    // Variables read by the expressions of each statement and of the values folded into it
+    reads: Vec<Vec<String>>,
+    // Folded values by the variable they defined, and those with side effects
+    folded: BTreeMap<String, Expression>,
+    effectful: BTreeSet<String>,
+    // Depth of the folded values, those they name included
+    depths: BTreeMap<String, usize>,
+}
+
+impl Block {
+    fn new(body: &[Statement], finalize: bool) -> Self {
+        let mut b = Self::default();
+        for (k, s) in body.iter().enumerate() {
+            let mut direct = Vec::new();
+            s.expressions().into_iter().for_each(|e| identifiers(e, &mut direct));
+            let mut all = direct.clone();
+            s.nested().into_iter().for_each(|s| statement_identifiers(s, &mut all));
+            for name in direct.iter() {
+                *b.direct.entry((*name).clone()).or_default() += 1;
+            }
+            for name in all {
+                *b.uses.entry(name.clone()).or_default() += 1;
+                b.first.entry(name.clone()).or_insert(k);
+            }
+            for name in s.assigned() {
+                b.assigned.entry(name.clone()).or_default().insert(k);
+            }
+            if statement_has_effects(s, finalize) {
+                b.effects.insert(k);
+            }
+            b.reads.push(direct.into_iter().cloned().collect());
+        }
+        b
+    }
+
+    fn count(map: &BTreeMap<String, usize>, name: &str) -> usize {
+        map.get(name).copied().unwrap_or_default()
+    }
+
+    // Whether `name` is assigned strictly between the statements `i` and `j`
+    fn assigned_between(&self, name: &str, i: usize, j: usize) -> bool {
+        self.assigned.get(name).is_some_and(|at| at.range(i + 1..j).next().is_some())
+    }
+
+    // `e` with the folded value it names, if any
+    fn resolve<'a>(&'a self, mut e: &'a Expression) -> &'a Expression {
+        while let Some(value) = match e {
+            Expression::Identifier(n) => self.folded.get(n),
+            _ => None,
+        } {
+            e = value;
+        }
+        e
+    }
+
+    // Index of the statement the assignment `i` of `name` to `value` can be folded into
+    fn consumer(&self, body: &[Option<Statement>], i: usize, name: &str, value: &Expression, finalize: bool) -> Option<usize> {
+        // Uses inside a nested block, e.g. in a branch of an `if`, stay where they are
+        if Self::count(&self.uses, name) != 1 || Self::count(&self.direct, name) != 1 {
+            return None;
+        }
+        let j = *self.first.get(name).filter(|j| **j > i)?;
+        let user = || body[j].iter().flat_map(|s| s.expressions());
+        if matches!(self.resolve(value), Expression::Composite { .. }) && user().any(|e| accessed(e, name)) {
+            return None;
+        }
+        let depth = self.depth(value, None);
+        if user().any(|e| self.depth(e, Some((name, depth))) > MAX_DEPTH) {
+            return None;
+        }
+        // The variables it reads must hold the same values where it moves
+        if self.reads[i].iter().any(|t| self.assigned_between(t, i, j)) {
+            return None;
+        }
+        if self.effects.contains(&i) {
+            if self.effects.range(i + 1..j).next().is_some() {
+                return None;
+            }
+            let mut effects = false;
+            if user().find_map(|e| evaluated_first(e, name, &mut effects, finalize, &self.effectful)) != Some(true) {
+                return None;
+            }
+        }
+        Some(j)
+    }
+
+    // Depth of `e` once the values folded into it are substituted, `name` standing for a value
+    // of depth `depth` when given
+    fn depth(&self, e: &Expression, name: Option<(&str, usize)>) -> usize {
+        match e {
+            Expression::Identifier(n) => match name {
+                Some((name, depth)) if n == name => depth,
+                _ => self.depths.get(n).copied().unwrap_or(1),
+            },
+            _ => 1 + e.operands().into_iter().map(|o| self.depth(o, name)).max().unwrap_or_default(),
+        }
+    }
+
+    // The definition `i` of `name` to `value` moved into the statement `j`
+    fn fold(&mut self, i: usize, j: usize, name: String, value: Expression) {
+        self.uses.remove(&name);
+        self.direct.remove(&name);
+        self.assigned.get_mut(&name).map(|at| at.remove(&i));
+        // Variables folded away are no longer read anywhere, only the others move along
+        let moved = core::mem::take(&mut self.reads[i]).into_iter().filter(|t| self.uses.contains_key(t)).collect::<Vec<_>>();
+        for t in moved.iter().filter(|t| Self::count(&self.uses, t) == 1) {
+            self.first.insert(t.clone(), j);
+        }
+        self.reads[j].extend(moved);
+        if self.effects.remove(&i) {
+            self.effects.insert(j);
+            self.effectful.insert(name.clone());
+        }
+        self.depths.insert(name.clone(), self.depth(&value, None));
+        self.folded.insert(name, value);
+    }
+}
+
+fn identifiers<'a>(e: &'a Expression, out: &mut Vec<&'a String>) {
+    match e {
+        Expression::Identifier(n) => out.push(n),
+        _ => e.operands().into_iter().for_each(|o| identifiers(o, out)),
+    }
+}
+
+pub(crate) fn statement_identifiers<'a>(s: &'a Statement, out: &mut Vec<&'a String>) {
+    s.expressions().into_iter().for_each(|e| identifiers(e, out));
+    s.nested().into_iter().for_each(|s| statement_identifiers(s, out));
+}
+
+// Registers are only folded within the block defining them, variables assigned more than once stay
+fn block(body: &mut Vec<Statement>, finalize: bool, reassigned: &BTreeSet<String>) {
+    let mut b = Block::new(body, finalize);
+    // Folded definitions are left empty until the end, so the indices stay valid
+    let mut statements: Vec<Option<Statement>> = body.drain(..).map(Some).collect();
+    for i in 0..statements.len() {
+        let consumer = match &statements[i] {
+            Some(Statement::Assign { targets, value, .. }) if targets.len() == 1 && !reassigned.contains(&targets[0]) => {
+                b.consumer(&statements, i, &targets[0], value, finalize)
+            },
+            _ => None,
+        };
+        if let Some(j) = consumer {
+            match statements[i].take() {
+                Some(Statement::Assign { mut targets, value, .. }) => b.fold(i, j, targets.remove(0), value),
+                _ => unreachable!("only assignments are inlined"),
+            }
+        }
+    }
+    *body = statements.into_iter().flatten()
+        .map(|mut s| {
+            s.expressions_mut().into_iter().for_each(|e| substitute(e, &mut b.folded));
+            s
+        })
+        .collect();
+}
+
+// Whether `function` can be reordered with other side effects
+fn pure_call(function: &str, finalize: bool) -> bool {
+    !finalize && (function.ends_with("::hash") || function.ends_with("::commit"))
+}
+
+fn has_effects(e: &Expression, finalize: bool) -> bool {
+    match e {
+        Expression::Call { function, .. } if !pure_call(function, finalize) => true,
+        _ => e.operands().into_iter().any(|o| has_effects(o, finalize)),
+    }
+}
+
+pub(crate) fn statement_has_effects(s: &Statement, finalize: bool) -> bool {
+    match s {
+        Statement::Expression(_) => true,
+        _ if s.nested().into_iter().any(|s| statement_has_effects(s, finalize)) => true,
+        _ => s.expressions().into_iter().any(|e| has_effects(e, finalize)),
+    }
+}
+
+// Whether a member of `name` is read, `point { x: r0 }.x` isn't valid Leo
+fn accessed(e: &Expression, name: &str) -> bool {
+    match e {
+        Expression::Member { inner, .. } if matches!(&**inner, Expression::Identifier(n) if n == name) => true,
+        _ => e.operands().into_iter().any(|o| accessed(o, name)),
+    }
+}
+
+// Whether `name` is evaluated before any side effect of `e` and unconditionally, `None` if `e`
+// doesn't use it. `effects` records the side effects of what was evaluated so far.
+// Values folded into `e` are still named, `effectful` are those evaluating a side effect.
+fn evaluated_first(e: &Expression, name: &str, effects: &mut bool, finalize: bool, effectful: &BTreeSet<String>) -> Option<bool> {
+    if let Expression::Identifier(n) = e {
+        *effects |= effectful.contains(n);
+        return (n == name).then_some(!*effects);
+    }
+    for (n, operand) in e.operands().into_iter().enumerate() {
+        let found = evaluated_first(operand, name, effects, finalize, effectful);
+        if let (Expression::Ternary { .. }, true, Some(_)) = (e, n > 0, found) {
+            return Some(false);
+        }
+        if found.is_some() {
+            return found;
+        }
+    }
+    if let Expression::Call { function, .. } = e {
+        *effects |= !pure_call(function, finalize);
+    }
+    None
+}
+
+// Replaces the names of folded values by the values, each is read once
+fn substitute(e: &mut Expression, folded: &mut BTreeMap<String, Expression>) {
+    while let Some(value) = match e {
+        Expression::Identifier(n) => folded.remove(n),
+        _ => None,
+    } {
+        *e = value;
+    }
+    e.operands_mut().into_iter().for_each(|o| substitute(o, folded));
+}
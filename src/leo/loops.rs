@@ -0,0 +1,324 @@
+// Re-rolling of unrolled `for` loops. Leo unrolls bounded loops, so a loop of N iterations over a
+// body of L statements becomes N consecutive runs of L statements with the same shape, differing
+// only in the registers they use and in literals growing with the iteration, e.g. the counter.
+// Each register or literal position across the runs must be one of:
+// - the same register defined before the loop, or the same literal
+// - a register defined earlier in the same iteration
+// - a register defined by the previous iteration, carried in a variable declared before the loop
+// - a literal `a + b * i` of an integer type, rewritten over the loop counter `i`
+// Registers defined in the loop are named after the last iteration, only the carried ones are
+// visible after it.
+use super::ast::{BinaryOp, Expression, Function, Statement, Type};
+use super::inline::statement_identifiers;
+use super::target;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+// Fewer repetitions read as well unrolled and are more likely a coincidence
+const MIN_ITERATIONS: usize = 3;
+
+// Longest loop body looked for, each length is a candidate at every statement
+const MAX_LENGTH: usize = 64;
+
+const INTEGER_TYPES: [(&str, u32, bool); 10] = [
+    ("u8", 8, false), ("u16", 16, false), ("u32", 32, false), ("u64", 64, false), ("u128", 128, false),
+    ("i8", 8, true), ("i16", 16, true), ("i32", 32, true), ("i64", 64, true), ("i128", 128, true),
+];
+
+pub fn function(f: &mut Function) {
+    let body = core::mem::take(&mut f.body);
+    let facts = Body::new(&body);
+    let mut start = 0;
+    while start < body.len() {
+        match facts.longest(start) {
+            Some((statements, length)) => {
+                f.body.extend(statements);
+                start += length;
+            },
+            None => {
+                f.body.push(body[start].clone());
+                start += 1;
+            },
+        }
+    }
+}
+
+// What the candidate loops of a body are checked against, computed once so a candidate costs
+// about the statements it covers rather than the whole body
+struct Body<'a> {
+    statements: &'a [Statement],
+    // `repeats[l - 1][s]`: how many statements from `s` have the shape of the one `l` after them
+    repeats: Vec<Vec<usize>>,
+    // Statements assigning each variable, in order
+    assigned: BTreeMap<&'a str, Vec<usize>>,
+    // Last statement reading each variable
+    last_use: BTreeMap<&'a str, usize>,
+}
+
+impl<'a> Body<'a> {
+    fn new(statements: &'a [Statement]) -> Self {
+        let mut ids = BTreeMap::new();
+        let shapes = statements.iter().map(|s| shape(s).map(|key| {
+            let next = ids.len();
+            *ids.entry(key).or_insert(next)
+        })).collect::<Vec<_>>();
+        let repeats = (1..=MAX_LENGTH.min(statements.len())).map(|length| {
+            let mut repeats = vec![0; statements.len() + 1];
+            for s in (0..statements.len().saturating_sub(length)).rev() {
+                if shapes[s].is_some() && shapes[s] == shapes[s + length] {
+                    repeats[s] = repeats[s + 1] + 1;
+                }
+            }
+            repeats
+        }).collect();
+        let mut assigned = BTreeMap::new();
+        let mut last_use = BTreeMap::new();
+        for (k, s) in statements.iter().enumerate() {
+            let mut reads = Vec::new();
+            statement_identifiers(s, &mut reads);
+            reads.into_iter().for_each(|name| {
+                last_use.insert(name.as_str(), k);
+            });
+            if let Some(t) = target(s) {
+                assigned.entry(t.as_str()).or_insert_with(Vec::new).push(k);
+            }
+        }
+        Self { statements, repeats, assigned, last_use }
+    }
+
+    // The re-rolled loop covering the most statements from `start`, with how many it replaces
+    fn longest(&self, start: usize) -> Option<(Vec<Statement>, usize)> {
+        let mut best: Option<(Vec<Statement>, usize)> = None;
+        for (length, repeats) in (1..).zip(self.repeats.iter()) {
+            let iterations = repeats[start] / length + 1;
+            if iterations < MIN_ITERATIONS || best.as_ref().is_some_and(|(_, covered)| *covered >= iterations * length) {
+                continue;
+            }
+            if let Some(statements) = self.reroll(start, length, iterations) {
+                best = Some((statements, iterations * length));
+            }
+        }
+        best
+    }
+}
+
+// Key equal for the statements a loop can repeat, up to registers and literals
+fn shape(s: &Statement) -> Option<String> {
+    let mut s = s.clone();
+    match &mut s {
+        Statement::Assign { targets, value, .. } if targets.len() == 1 => {
+            targets[0].clear();
+            blank(value);
+        },
+        Statement::Expression(value) => blank(value),
+        _ => return None,
+    }
+    Some(format!("{:?}", s))
+}
+
+// Registers and literals alike become an empty literal
+fn blank(e: &mut Expression) {
+    match e {
+        Expression::Identifier(_) | Expression::Literal(_) => *e = Expression::Literal(String::new()),
+        _ => e.operands_mut().into_iter().for_each(blank),
+    }
+}
+
+// Registers and literals, in the order `replace` visits them
+fn leaves<'a>(e: &'a Expression, out: &mut Vec<&'a Expression>) {
+    match e {
+        Expression::Identifier(_) | Expression::Literal(_) => out.push(e),
+        _ => e.operands().into_iter().for_each(|o| leaves(o, out)),
+    }
+}
+
+fn replace(e: &mut Expression, with: &mut impl Iterator<Item = Expression>) {
+    match e {
+        Expression::Identifier(_) | Expression::Literal(_) => *e = with.next().unwrap(),
+        _ => e.operands_mut().into_iter().for_each(|o| replace(o, with)),
+    }
+}
+
+fn value(s: &Statement) -> &Expression {
+    match s {
+        Statement::Assign { value, .. } | Statement::Expression(value) => value,
+        _ => unreachable!("loops only hold assignments and expressions"),
+    }
+}
+
+// Value and type suffix of an integer literal, e.g. `-5i8`
+fn integer(literal: &str) -> Option<(i128, &str)> {
+    let split = literal.find(|c: char| !c.is_ascii_digit() && c != '-')?;
+    let (value, suffix) = literal.split_at(split);
+    INTEGER_TYPES.iter().any(|(t, ..)| *t == suffix).then_some(())?;
+    Some((value.parse().ok()?, suffix))
+}
+
+fn literal(e: &Expression) -> Option<(i128, &str)> {
+    match e {
+        Expression::Literal(l) => integer(l),
+        _ => None,
+    }
+}
+
+// `first + step * i` over the loop counter `i`
+fn affine(first: i128, step: i128, suffix: &str) -> Expression {
+    let counter = Expression::Identifier("i".to_string());
+    let literal = |v: i128| Expression::Literal(format!("{}{}", v, suffix));
+    let term = match step.unsigned_abs() {
+        1 => counter,
+        s => Expression::Binary { op: BinaryOp::Mul, left: Box::new(literal(s as i128)), right: Box::new(counter) },
+    };
+    match (first, step > 0) {
+        (0, true) => term,
+        (_, true) => Expression::Binary { op: BinaryOp::Add, left: Box::new(literal(first)), right: Box::new(term) },
+        (_, false) => Expression::Binary { op: BinaryOp::Sub, left: Box::new(literal(first)), right: Box::new(term) },
+    }
+}
+
+// Largest value of the integer type `suffix`
+fn max(suffix: &str) -> Option<u128> {
+    INTEGER_TYPES.iter().find(|(t, ..)| *t == suffix).map(|(_, bits, signed)| u128::MAX >> (128 - bits + *signed as u32))
+}
+
+// Whether `count` iterations fit a counter of type `suffix`
+fn fits(count: usize, suffix: &str) -> bool {
+    max(suffix).is_some_and(|m| count as u128 <= m)
+}
+
+// Whether `step * i` fits the type `suffix` on every iteration. Leo checks each operation, the
+// literals `first ± step * i` being in range doesn't keep the product from overflowing, e.g.
+// `100i8 - 100i8 * i` for `100i8`, `0i8`, `-100i8`.
+fn step_fits(step: i128, iterations: usize, suffix: &str) -> bool {
+    let step = step.unsigned_abs();
+    let largest = step.checked_mul(iterations as u128 - 1);
+    max(suffix).is_some_and(|m| step <= m && largest.is_some_and(|l| l <= m))
+}
+
+// How a register or literal position of the loop body changes from one iteration to the next
+enum Slot<'a> {
+    // The same register or literal, defined before the loop
+    Same(&'a Expression),
+    // The register defined earlier in the same iteration at the given position
+    Local(usize),
+    // The register defined by the previous iteration at the given position
+    Carried(usize),
+    // The literal `first + step * k` in iteration `k`, of the integer type `suffix`
+    Counter(i128, i128, &'a str),
+}
+
+impl<'a> Body<'a> {
+    // Iteration and position of the last assignment of `name` among the `length` statements
+    // long runs from `start` to `end`
+    fn defined(&self, name: &str, start: usize, end: usize, length: usize) -> Option<(usize, usize)> {
+        let at = self.assigned.get(name)?;
+        let s = *at[..at.partition_point(|s| *s < end)].last().filter(|s| **s >= start)?;
+        Some(((s - start) / length, (s - start) % length))
+    }
+
+    // The declarations of the carried registers followed by the loop, if every register and
+    // literal of the `iterations` runs of `length` statements from `start` follows one of the
+    // patterns. The first two runs give the pattern of each position, the others are checked
+    // against it and the first mismatch gives up.
+    fn reroll(&self, start: usize, length: usize, iterations: usize) -> Option<Vec<Statement>> {
+        let body = self.statements;
+        let run = |k: usize| &body[start + k * length..start + (k + 1) * length];
+        let end = start + iterations * length;
+        let register = |e: &Expression| match e {
+            Expression::Identifier(n) => self.defined(n, start, end, length),
+            _ => None,
+        };
+        let last = run(iterations - 1);
+        let name = |p: usize| target(&last[p]).cloned().unwrap_or_default();
+        // Initial value of each carried position, and the type of the counter if a literal follows it
+        let mut carried: BTreeMap<usize, &Expression> = BTreeMap::new();
+        let mut counter: Option<&str> = None;
+        let mut statements = Vec::new();
+        for (p, statement) in last.iter().enumerate() {
+            let leaves_of = |k: usize| {
+                let mut out = Vec::new();
+                leaves(value(&run(k)[p]), &mut out);
+                out
+            };
+            let mut slots = Vec::new();
+            for (a, b) in leaves_of(0).into_iter().zip(leaves_of(1)) {
+                slots.push(match (register(a), register(b)) {
+                    (None, _) if a == b => Slot::Same(a),
+                    (Some((0, q)), Some((1, r))) if q == r && q < p => Slot::Local(q),
+                    (None, Some((0, q))) if p <= q => {
+                        if *carried.entry(q).or_insert(a) != a {
+                            return None;
+                        }
+                        Slot::Carried(q)
+                    },
+                    _ => {
+                        let ((first, suffix), (second, other)) = (literal(a)?, literal(b)?);
+                        let step = second.checked_sub(first)?;
+                        if other != suffix || *counter.get_or_insert(suffix) != suffix || !step_fits(step, iterations, suffix) {
+                            return None;
+                        }
+                        Slot::Counter(first, step, suffix)
+                    },
+                });
+            }
+            for k in 2..iterations {
+                let follows = |(slot, v): (&Slot, &Expression)| match slot {
+                    Slot::Same(a) => v == *a,
+                    Slot::Local(q) => register(v) == Some((k, *q)),
+                    Slot::Carried(q) => register(v) == Some((k - 1, *q)),
+                    Slot::Counter(first, step, suffix) => {
+                        let expected = step.checked_mul(k as i128).and_then(|d| d.checked_add(*first));
+                        literal(v).is_some_and(|(v, s)| s == *suffix && Some(v) == expected)
+                    },
+                };
+                if !slots.iter().zip(leaves_of(k)).all(follows) {
+                    return None;
+                }
+            }
+            let mut replacements = slots.into_iter().map(|slot| match slot {
+                Slot::Same(a) => a.clone(),
+                Slot::Local(q) | Slot::Carried(q) => Expression::Identifier(name(q)),
+                Slot::Counter(first, step, suffix) => affine(first, step, suffix),
+            });
+            let mut statement = statement.clone();
+            match &mut statement {
+                Statement::Assign { value, .. } | Statement::Expression(value) => replace(value, &mut replacements),
+                _ => unreachable!("loops only hold assignments and expressions"),
+            }
+            statements.push(statement);
+        }
+        // The counter would shadow a mapping of the same name
+        if body[start..end].iter().any(|s| s.uses("i") > 0) {
+            return None;
+        }
+        // Only the final value of a carried register can be read after the loop
+        for t in body[start..end].iter().filter_map(target) {
+            let after = self.last_use.get(t.as_str()).is_some_and(|s| *s >= end);
+            let (k, p) = self.defined(t, start, end, length)?;
+            if after && (k != iterations - 1 || !carried.contains_key(&p)) {
+                return None;
+            }
+        }
+        let counter = counter.unwrap_or("u32");
+        if !fits(iterations, counter) {
+            return None;
+        }
+        // Carried registers are declared with their initial value and assigned in the loop
+        let mut declarations = Vec::new();
+        for (q, initial) in carried.iter() {
+            if let Statement::Assign { targets, ty, .. } = &mut statements[*q] {
+                declarations.push(Statement::Assign { targets: targets.clone(), ty: ty.take(), value: (*initial).clone() });
+            }
+        }
+        declarations.push(Statement::For {
+            variable: "i".to_string(),
+            ty: Type::Named(counter.to_string()),
+            start: Expression::Literal(format!("0{}", counter)),
+            end: Expression::Literal(format!("{}{}", iterations, counter)),
+            body: statements,
+        });
+        Some(declarations)
+    }
+}
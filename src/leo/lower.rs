@@ -0,0 +1,357 @@
+// Lowering of the decoded program into the Leo syntax tree
+use crate::Disassembler;
+use crate::components::{Component, Function, Mapping, Record, Struct};
+use crate::components::commands::Command;
+use crate::components::finalize::Finalize;
+use crate::components::function::FunctionType;
+use crate::components::instructions::{Instruction, Opcode, Operand, Output};
+use crate::components::registers::{IoRegister, Register};
+use crate::components::types::{self, Attribute};
+use crate::output::Assembly;
+use crate::inference::{Infer, RegisterTypes};
+use super::ast::{self, BinaryOp, Expression, FunctionKind, Item, Statement, UnaryOp, Visibility};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Default)]
+pub struct Lower<'a> {
+    // Declarations of the program, to name the members of constructed structs and records
+    program: Option<&'a Disassembler>,
+}
+
+fn visibility(attribute: &Attribute) -> Option<Visibility> {
+    match attribute {
+        Attribute::Constant => Some(Visibility::Constant),
+        Attribute::Public => Some(Visibility::Public),
+        Attribute::Private => Some(Visibility::Private),
+        Attribute::Record | Attribute::ExternalRecord => None,
+    }
+}
+
+fn leo_type(value_type: &types::Type) -> ast::Type {
+    ast::Type::Named(value_type.leo())
+}
+
+pub fn register(register: &Register) -> Expression {
+    register.identifiers().iter().fold(Expression::Identifier(format!("r{}", register.locator())), |inner, name| {
+        Expression::Member { inner: Box::new(inner), name: name.clone() }
+    })
+}
+
+pub fn operand(operand: &Operand) -> Expression {
+    match operand {
+        Operand::Register(r) => register(r),
+        Operand::Literal(l) => Expression::Literal(l.leo()),
+        Operand::ProgramId(l) => Expression::Identifier(l.leo()),
+        Operand::Caller => Expression::Identifier("self.caller".to_string()),
+    }
+}
+
+fn call(function: &str, arguments: Vec<Expression>) -> Expression {
+    Expression::Call { function: function.to_string(), arguments }
+}
+
+// `Mapping::get(account, r0)` and the like
+fn mapping_call(function: &str, mapping: &str, arguments: &[&Operand]) -> Expression {
+    let mut all = vec![Expression::Identifier(mapping.to_string())];
+    all.extend(arguments.iter().map(|o| operand(o)));
+    call(function, all)
+}
+
+// What an instruction becomes in Leo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Binary(BinaryOp),
+    Unary(UnaryOp),
+    // `receiver.name(arguments)`, e.g. `r0.add_wrapped(r1)`
+    Method(&'static str),
+    // Built-in function, e.g. `BHP256::hash(r0)` or `assert_eq(r0, r1)`
+    Function(&'static str),
+    // Call of the function or closure named by the first operand
+    Call,
+    // Construction of the struct or record the instruction casts to
+    Cast,
+    Ternary,
+}
+
+pub fn operation(opcode: Opcode) -> Operation {
+    match opcode {
+        Opcode::Abs => Operation::Method("abs"),
+        Opcode::AbsWrapped => Operation::Method("abs_wrapped"),
+        Opcode::Add => Operation::Binary(BinaryOp::Add),
+        Opcode::AddWrapped => Operation::Method("add_wrapped"),
+        Opcode::And => Operation::Binary(BinaryOp::BitAnd),
+        Opcode::AssertEq => Operation::Function("assert_eq"),
+        Opcode::AssertNeq => Operation::Function("assert_neq"),
+        Opcode::CommitBHP256 => Operation::Function("BHP256::commit"),
+        Opcode::CommitBHP512 => Operation::Function("BHP512::commit"),
+        Opcode::CommitBHP768 => Operation::Function("BHP768::commit"),
+        Opcode::CommitBHP1024 => Operation::Function("BHP1024::commit"),
+        Opcode::CommitPED64 => Operation::Function("PED64::commit"),
+        Opcode::CommitPED128 => Operation::Function("PED128::commit"),
+        Opcode::Div => Operation::Binary(BinaryOp::Div),
+        Opcode::DivWrapped => Operation::Method("div_wrapped"),
+        Opcode::Double => Operation::Method("double"),
+        Opcode::GreaterThan => Operation::Binary(BinaryOp::Gt),
+        Opcode::GreaterThanOrEqual => Operation::Binary(BinaryOp::Gte),
+        Opcode::HashBHP256 => Operation::Function("BHP256::hash"),
+        Opcode::HashBHP512 => Operation::Function("BHP512::hash"),
+        Opcode::HashBHP768 => Operation::Function("BHP768::hash"),
+        Opcode::HashBHP1024 => Operation::Function("BHP1024::hash"),
+        Opcode::HashPED64 => Operation::Function("PED64::hash"),
+        Opcode::HashPED128 => Operation::Function("PED128::hash"),
+        Opcode::HashPSD2 => Operation::Function("PSD2::hash"),
+        Opcode::HashPSD4 => Operation::Function("PSD4::hash"),
+        Opcode::HashPSD8 => Operation::Function("PSD8::hash"),
+        Opcode::Inv => Operation::Method("inv"),
+        Opcode::IsEq => Operation::Binary(BinaryOp::Eq),
+        Opcode::IsNeq => Operation::Binary(BinaryOp::Neq),
+        Opcode::LessThan => Operation::Binary(BinaryOp::Lt),
+        Opcode::LessThanOrEqual => Operation::Binary(BinaryOp::Lte),
+        Opcode::Mod => Operation::Method("mod"),
+        Opcode::Mul => Operation::Binary(BinaryOp::Mul),
+        Opcode::MulWrapped => Operation::Method("mul_wrapped"),
+        Opcode::Nand => Operation::Method("nand"),
+        Opcode::Neg => Operation::Unary(UnaryOp::Negate),
+        Opcode::Nor => Operation::Method("nor"),
+        Opcode::Not => Operation::Unary(UnaryOp::Not),
+        Opcode::Or => Operation::Binary(BinaryOp::BitOr),
+        Opcode::Pow => Operation::Binary(BinaryOp::Pow),
+        Opcode::PowWrapped => Operation::Method("pow_wrapped"),
+        Opcode::Rem => Operation::Binary(BinaryOp::Rem),
+        Opcode::RemWrapped => Operation::Method("rem_wrapped"),
+        Opcode::Shl => Operation::Binary(BinaryOp::Shl),
+        Opcode::ShlWrapped => Operation::Method("shl_wrapped"),
+        Opcode::Shr => Operation::Binary(BinaryOp::Shr),
+        Opcode::ShrWrapped => Operation::Method("shr_wrapped"),
+        Opcode::Square => Operation::Method("square"),
+        Opcode::SquareRoot => Operation::Method("square_root"),
+        Opcode::Sub => Operation::Binary(BinaryOp::Sub),
+        Opcode::SubWrapped => Operation::Method("sub_wrapped"),
+        Opcode::Xor => Operation::Binary(BinaryOp::BitXor),
+        Opcode::Call => Operation::Call,
+        Opcode::Cast => Operation::Cast,
+        Opcode::Ternary => Operation::Ternary,
+    }
+}
+
+impl<'a> Lower<'a> {
+    pub fn new(program: &'a Disassembler) -> Self {
+        Self { program: Some(program) }
+    }
+
+    pub fn program(&self, program: &Disassembler) -> ast::Program {
+        let (name, network) = program.get_program_id();
+        ast::Program {
+            imports: program.imports().iter().map(|(p, n)| format!("{}.{}", p, n)).collect(),
+            id: format!("{}.{}", name, network),
+            items: program.components().iter().flat_map(|c| self.component(c)).collect(),
+        }
+    }
+
+    // A function lowers to its transition followed by its finalize block, if any
+    pub fn component(&self, component: &Component) -> Vec<Item> {
+        match component {
+            Component::Mapping(m) => vec![Item::Mapping(self.mapping(m))],
+            Component::Struct(s) => vec![Item::Struct(self.structure(s))],
+            Component::Record(r) => vec![Item::Record(self.record(r))],
+            Component::Function(f) => self.function_items(f),
+        }
+    }
+
+    pub fn function_items(&self, function: &Function) -> Vec<Item> {
+        let mut items = vec![Item::Function(self.function(function))];
+        items.extend(function.finalize().map(|f| Item::Function(self.finalize(f))));
+        items
+    }
+
+    pub fn mapping(&self, mapping: &Mapping) -> ast::Mapping {
+        ast::Mapping {
+            name: mapping.name().to_string(),
+            key: leo_type(mapping.key().value_type()),
+            value: leo_type(mapping.value().value_type()),
+        }
+    }
+
+    pub fn structure(&self, structure: &Struct) -> ast::Composite {
+        let members = structure.entries().iter()
+            .map(|e| ast::Member { name: e.name().to_string(), ty: leo_type(e.value_type()), visibility: None })
+            .collect();
+        ast::Composite { name: structure.name().to_string(), members }
+    }
+
+    pub fn record(&self, record: &Record) -> ast::Composite {
+        let mut members = vec![
+            ast::Member { name: "owner".to_string(), ty: ast::Type::Named("address".to_string()), visibility: visibility(record.owner()) },
+            ast::Member { name: "gates".to_string(), ty: ast::Type::Named("u64".to_string()), visibility: visibility(record.gates()) },
+        ];
+        members.extend(record.entries().iter().map(|e| ast::Member {
+            name: e.name().to_string(),
+            ty: leo_type(e.value_type()),
+            visibility: visibility(e.attribute()),
+        }));
+        ast::Composite { name: record.name().to_string(), members }
+    }
+
+    fn parameter(&self, input: &IoRegister) -> ast::Parameter {
+        ast::Parameter {
+            // Finalize inputs are always public and written without it
+            visibility: match input.function_type() {
+                FunctionType::Finalize => None,
+                _ => input.attribute().and_then(visibility),
+            },
+            name: format!("r{}", input.register().locator()),
+            ty: leo_type(input.value_type()),
+        }
+    }
+
+    fn infer(&self) -> Infer<'a> {
+        match self.program {
+            Some(program) => Infer::new(program),
+            None => Infer::default(),
+        }
+    }
+
+    pub fn function(&self, function: &Function) -> ast::Function {
+        let types = self.infer().function(function);
+        let mut body: Vec<Statement> = function.instructions().iter().map(|i| self.instruction(i, &types)).collect();
+        let values: Vec<Expression> = function.outputs().iter().map(|o| register(o.register())).collect();
+        let finalize = function.finalize_operands().map(|operands| operands.iter().map(operand).collect::<Vec<_>>());
+        if !values.is_empty() || finalize.is_some() {
+            body.push(Statement::Return { values, finalize });
+        }
+        ast::Function {
+            kind: match function.function_type() {
+                FunctionType::Closure => FunctionKind::Function,
+                _ => FunctionKind::Transition,
+            },
+            name: function.name().to_string(),
+            inputs: function.inputs().iter().map(|i| self.parameter(i)).collect(),
+            outputs: function.outputs().iter().map(|o| ast::Output {
+                // Only public outputs are marked, private is the default
+                visibility: o.attribute().and_then(visibility).filter(|v| *v == Visibility::Public),
+                ty: leo_type(o.value_type()),
+            }).collect(),
+            body,
+        }
+    }
+
+    pub fn finalize(&self, finalize: &Finalize) -> ast::Function {
+        ast::Function {
+            kind: FunctionKind::Finalize,
+            name: finalize.name().to_string(),
+            inputs: finalize.inputs().iter().map(|i| self.parameter(i)).collect(),
+            outputs: Vec::new(),
+            body: {
+                let types = self.infer().finalize(finalize);
+                finalize.commands().iter().map(|c| self.command(c, &types)).collect()
+            },
+        }
+    }
+
+    // Member names of the struct or record `name`, in declaration order
+    fn members(&self, value_type: &types::Type) -> Option<Vec<String>> {
+        let program = self.program?;
+        match value_type {
+            types::Type::Other(name) => program.structure(name).map(|s| s.entries().iter().map(|e| e.name().to_string()).collect()),
+            types::Type::Record(name) => program.record(name).map(|r| {
+                ["owner", "gates"].iter().map(|m| m.to_string()).chain(r.entries().iter().map(|e| e.name().to_string())).collect()
+            }),
+            _ => None,
+        }
+    }
+
+    fn cast(&self, operands: Vec<Expression>, value_type: &types::Type) -> Expression {
+        let names = self.members(value_type).filter(|names| names.len() == operands.len());
+        let members = match names {
+            Some(names) => names.into_iter().map(Some).zip(operands).collect(),
+            None => operands.into_iter().map(|o| (None, o)).collect(),
+        };
+        Expression::Composite { name: value_type.leo(), members }
+    }
+
+    // Declared type of the registers a statement defines, when all of them are known
+    fn declared(registers: &[&Register], types: &RegisterTypes) -> Option<ast::Type> {
+        let known = registers.iter().map(|r| types.get(r.locator()).map(leo_type)).collect::<Option<Vec<_>>>()?;
+        match known.len() {
+            0 => None,
+            1 => known.into_iter().next(),
+            _ => Some(ast::Type::Tuple(known)),
+        }
+    }
+
+    pub fn instruction(&self, instruction: &Instruction, types: &RegisterTypes) -> Statement {
+        let mut operands: Vec<Expression> = instruction.operands().iter().map(operand).collect();
+        let binary = |op, operands: Vec<Expression>| {
+            let mut operands = operands.into_iter();
+            Expression::Binary { op, left: Box::new(operands.next().unwrap()), right: Box::new(operands.next().unwrap()) }
+        };
+        let method = |name: &str, operands: Vec<Expression>| {
+            let mut operands = operands.into_iter();
+            Expression::Method { receiver: Box::new(operands.next().unwrap()), name: name.to_string(), arguments: operands.collect() }
+        };
+        let unary = |op, operands: Vec<Expression>| Expression::Unary { op, operand: Box::new(operands.into_iter().next().unwrap()) };
+        // Instructions edited into a shape their opcode cannot have, e.g. an `add` stripped of its
+        // operands, are written as a call to their mnemonic rather than lowered
+        let well_formed = match operation(instruction.opcode()) {
+            Operation::Binary(_) => operands.len() == 2,
+            Operation::Unary(_) => operands.len() == 1,
+            Operation::Ternary => operands.len() == 3,
+            Operation::Method(_) | Operation::Call => !operands.is_empty(),
+            Operation::Function(_) => true,
+            Operation::Cast => matches!(instruction.output(), Output::Cast(_)),
+        };
+        let value = match operation(instruction.opcode()) {
+            _ if !well_formed => call(&instruction.opcode().assembly(), operands),
+            Operation::Binary(op) => binary(op, operands),
+            Operation::Unary(op) => unary(op, operands),
+            Operation::Method(name) => method(name, operands),
+            Operation::Function(name) => call(name, operands),
+            Operation::Call => {
+                operands.remove(0);
+                let callee = instruction.callee().map(|c| c.leo()).unwrap_or_default();
+                call(&callee, operands)
+            },
+            Operation::Cast => match instruction.output() {
+                Output::Cast((_, value_type)) => self.cast(operands, value_type),
+                _ => unreachable!("checked to have a typed output"),
+            },
+            Operation::Ternary => {
+                let mut operands = operands.into_iter().map(Box::new);
+                Expression::Ternary {
+                    condition: operands.next().unwrap(),
+                    if_true: operands.next().unwrap(),
+                    if_false: operands.next().unwrap(),
+                }
+            },
+        };
+        let destinations = instruction.destinations();
+        match destinations.is_empty() {
+            true => Statement::Expression(value),
+            false => Statement::Assign {
+                targets: destinations.iter().map(|r| r.leo()).collect(),
+                ty: Self::declared(&destinations, types),
+                value,
+            },
+        }
+    }
+
+    pub fn command(&self, command: &Command, types: &RegisterTypes) -> Statement {
+        let assign = |destination: &Register, value| Statement::Assign {
+            targets: vec![destination.leo()],
+            ty: Self::declared(&[destination], types),
+            value,
+        };
+        match command {
+            Command::Instruction(i) => self.instruction(i, types),
+            Command::Contains { mapping, key, destination } => assign(destination, mapping_call("Mapping::contains", mapping, &[key])),
+            Command::Get { mapping, key, destination } => assign(destination, mapping_call("Mapping::get", mapping, &[key])),
+            Command::GetOrUse { mapping, key, default, destination } => assign(destination, mapping_call("Mapping::get_or_use", mapping, &[key, default])),
+            Command::Decrement { mapping, key, value } => Statement::Expression(mapping_call("decrement", mapping, &[key, value])),
+            Command::Increment { mapping, key, value } => Statement::Expression(mapping_call("increment", mapping, &[key, value])),
+            Command::Remove { mapping, key } => Statement::Expression(mapping_call("Mapping::remove", mapping, &[key])),
+            Command::Set { mapping, key, value } => Statement::Expression(mapping_call("Mapping::set", mapping, &[key, value])),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+// Decompilation to Leo: the decoded program is lowered into a Leo syntax tree (`ast`),
+// restructured (`loops`, `conditionals`, `inline`), then printed (`printer`)
+pub mod ast;
+pub mod conditionals;
+pub mod inline;
+pub mod loops;
+pub mod lower;
+pub mod printer;
+
+use crate::Disassembler;
+use crate::components::Function;
+use crate::components::finalize::Finalize;
+use ast::{FunctionKind, Statement};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Optional restructuring of the decompiled Leo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeoOptions {
+    // Re-roll runs of identical statements back into the `for` loops they were unrolled from
+    pub reroll_loops: bool,
+}
+
+impl Default for LeoOptions {
+    fn default() -> Self {
+        Self { reroll_loops: true }
+    }
+}
+
+pub fn decompile(program: &Disassembler) -> String {
+    decompile_with(program, LeoOptions::default())
+}
+
+pub fn decompile_with(program: &Disassembler, options: LeoOptions) -> String {
+    let mut ast = lower::Lower::new(program).program(program);
+    for item in ast.items.iter_mut() {
+        if let ast::Item::Function(f) = item {
+            restructure(f, options);
+        }
+    }
+    printer::program(&ast)
+}
+
+// Restructuring of one lowered function, the passes depend on the order
+pub fn restructure(f: &mut ast::Function, options: LeoOptions) {
+    if options.reroll_loops {
+        loops::function(f);
+    }
+    conditionals::function(f);
+    inline::function(f);
+}
+
+// Runs `pass` on the body of `f`, then on the blocks nested in it, with whether `f` is a finalize
+// block and the variables assigned more than once, e.g. carried across loop iterations
+pub(crate) fn blocks(f: &mut ast::Function, pass: fn(&mut Vec<Statement>, bool, &BTreeSet<String>)) {
+    let reassigned = ast::reassigned(&f.body);
+    walk(&mut f.body, f.kind == FunctionKind::Finalize, &reassigned, pass);
+}
+
+fn walk(body: &mut Vec<Statement>, finalize: bool, reassigned: &BTreeSet<String>, pass: fn(&mut Vec<Statement>, bool, &BTreeSet<String>)) {
+    pass(body, finalize, reassigned);
+    for s in body.iter_mut() {
+        match s {
+            Statement::If { then, otherwise, .. } => {
+                walk(then, finalize, reassigned, pass);
+                walk(otherwise, finalize, reassigned, pass);
+            },
+            Statement::For { body, .. } => walk(body, finalize, reassigned, pass),
+            _ => {},
+        }
+    }
+}
+
+// Variable an assignment defines, the first one for tuples
+pub(crate) fn target(s: &Statement) -> Option<&String> {
+    match s {
+        Statement::Assign { targets, .. } => targets.first(),
+        _ => None,
+    }
+}
+
+// A function and its finalize block on their own, restructured like in `decompile`. Without the
+// rest of the program the results of calls and mapping reads have no type and casts no member names.
+pub fn function(function: &Function) -> String {
+    lower::Lower::default().function_items(function).into_iter()
+        .map(|mut item| {
+            if let ast::Item::Function(f) = &mut item {
+                restructure(f, LeoOptions::default());
+            }
+            printer::item(&item)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn finalize(finalize: &Finalize) -> String {
+    let mut f = lower::Lower::default().finalize(finalize);
+    restructure(&mut f, LeoOptions::default());
+    printer::item(&ast::Item::Function(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ast::{BinaryOp, Expression};
+    use super::*;
+    use crate::Assembly;
+    use crate::components::instructions::Opcode;
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    fn binary(op: BinaryOp, left: Expression, right: Expression) -> Expression {
+        Expression::Binary { op, left: Box::new(left), right: Box::new(right) }
+    }
+
+    fn name(s: &str) -> Expression {
+        Expression::Identifier(s.to_string())
+    }
+
+    #[test]
+    fn parenthesizes_by_precedence() {
+        let sum = binary(BinaryOp::Add, name("a"), name("b"));
+        assert_eq!(printer::expression(&binary(BinaryOp::Mul, sum.clone(), name("c"))), "(a + b) * c");
+        assert_eq!(printer::expression(&binary(BinaryOp::Sub, name("c"), sum.clone())), "c - (a + b)");
+        assert_eq!(printer::expression(&binary(BinaryOp::Sub, sum.clone(), name("c"))), "a + b - c");
+        let method = Expression::Method { receiver: Box::new(sum), name: "abs".to_string(), arguments: vec![] };
+        assert_eq!(printer::expression(&method), "(a + b).abs()");
+    }
+
+    #[test]
+    fn lowers_instructions() {
+        let source = "program test.aleo;\n\nstruct point:\n    x as i8;\n    y as i8;\n\nfunction f:\n    input r0 as i8.private;\n    abs.w r0 into r1;\n    assert.neq r0 r1;\n    not r0 into r2;\n    cast r1 r2 into r3 as point;\n    output r3 as point.private;\n";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let f = decompile(&a);
+        assert!(f.contains("\tlet r1: i8 = r0.abs_wrapped();\n\tassert_neq(r0, r1);\n\treturn point { x: r1, y: !r0 };\n"));
+        assert_eq!(a.function("f").unwrap().instructions()[0].leo(), "r1 = r0.abs_wrapped()");
+        let instructions = a.function("f").unwrap().instructions();
+        let opcodes = instructions.iter().map(|i| i.opcode().leo()).collect::<Vec<String>>();
+        assert_eq!(opcodes, ["abs_wrapped", "assert_neq", "!", ""]);
+        assert_eq!(instructions[3].operands().to_vec().leo(), "r1, r2");
+        assert_eq!(Opcode::Add.leo(), "+");
+        assert_eq!(Opcode::HashBHP256.leo(), "BHP256::hash");
+    }
+
+    #[test]
+    fn lowers_malformed_instructions() {
+        use crate::components::Component;
+        let source = "program test.aleo;\n\nclosure c:\n    input r0 as u64;\n    output r0 as u64;\n\n\
+            function f:\n    input r0 as u64.private;\n    input r1 as boolean.private;\n    add r0 r0 into r2;\n    not r1 into r3;\n    \
+            ternary r1 r0 r2 into r4;\n    add.w r0 r4 into r5;\n    call c r5 into r6;\n    assert.eq r6 r0;\n    output r6 as u64.private;\n";
+        let mut a = Disassembler::from_aleo(source).expect("malformed source");
+        if let Some(Component::Function(f)) = a.components_mut().get_mut(1) {
+            f.instructions_mut().iter_mut().for_each(|i| i.operands_mut().clear());
+        }
+        let leo = a.leo();
+        assert!(leo.contains("\tr2 = add();\n\tr3 = not();\n\tr4 = ternary();\n\tr5 = add.w();\n\tr6 = call();\n\tassert_eq();\n\treturn r6;\n"), "{}", leo);
+    }
+
+    #[test]
+    fn inlines_single_use_registers() {
+        let source = "program test.aleo;
+
+mapping account:
+    key owner as address.public;
+    value amount as u64.public;
+
+closure first:
+    input r0 as u64;
+    output r0 as u64;
+
+closure second:
+    input r0 as u64;
+    output r0 as u64;
+
+function f:
+    input r0 as u64.private;
+    input r1 as u64.private;
+    input r2 as u64.private;
+    sub r0 r1 into r3;
+    mul r3 r2 into r4;
+    call first r0 into r5;
+    call second r1 into r6;
+    add r6 r5 into r7;
+    is.eq r7 r4 into r8;
+    ternary r8 r5 r4 into r9;
+    output r9 as u64.private;
+    finalize self.caller;
+
+finalize f:
+    input r0 as address.public;
+    get account[r0] into r1;
+    set 0u64 into account[r0];
+    add r1 1u64 into r2;
+    set r2 into account[r0];
+";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let f = decompile(&a);
+        // `first` stays ahead of `second`, used twice `r4` and `r5` stay
+        assert!(f.contains("\tlet r4: u64 = (r0 - r1) * r2;\n\tlet r5: u64 = first(r0);\n\treturn second(r1) + r5 == r4 ? r5 : r4 then finalize(self.caller);\n"));
+        // The read can't move past the write
+        assert!(f.contains("\tlet r1: u64 = Mapping::get(account, r0);\n\tMapping::set(account, r0, 0u64);\n\tMapping::set(account, r0, r1 + 1u64);\n"));
+        // On its own a function is folded the same way, only the types it can't know are missing
+        let f = a.function("f").unwrap().leo();
+        assert!(f.contains("\tlet r4: u64 = (r0 - r1) * r2;\n\tr5 = first(r0);\n\treturn second(r1) + r5 == r4 ? r5 : r4 then finalize(self.caller);\n"));
+        assert!(f.contains("\tMapping::set(account, r0, r1 + 1u64);\n"));
+    }
+
+    // `f` adding 1u64 to its input `n - 1` times, one register after the other
+    fn chain(n: usize) -> Disassembler {
+        let mut source = String::from("program test.aleo;\n\nfunction f:\n    input r0 as u64.private;\n");
+        for k in 1..n {
+            source.push_str(&format!("    add r{} 1u64 into r{};\n", k - 1, k));
+        }
+        source.push_str(&format!("    output r{} as u64.private;\n", n - 1));
+        Disassembler::from_aleo(&source).expect("malformed source")
+    }
+
+    #[test]
+    fn inlines_long_chains() {
+        // Every register is read once by the next one, the chain folds into the return but for
+        // a `let` every 63 additions that keeps expressions shallow
+        let f = decompile_with(&chain(300), LeoOptions { reroll_loops: false });
+        let add = " + 1u64".repeat(63);
+        let lets = format!("\tlet r63: u64 = r0{0};\n\tlet r126: u64 = r63{0};\n\tlet r189: u64 = r126{0};\n\tlet r252: u64 = r189{0};\n", add);
+        assert!(f.contains(&format!("{}\treturn r252{};\n", lets, " + 1u64".repeat(47))), "{}", f);
+
+        // Deep enough to overflow the stack of the recursive walkers if it folded into one expression
+        let f = decompile_with(&chain(20_000), LeoOptions { reroll_loops: false });
+        assert_eq!(f.matches("\tlet ").count(), 317);
+        assert!(f.contains(&format!("\treturn r19971{};\n", " + 1u64".repeat(28))));
+    }
+
+    #[test]
+    fn recovers_if_else() {
+        let source = "program test.aleo;
+
+closure double:
+    input r0 as u64;
+    add r0 r0 into r1;
+    output r1 as u64;
+
+function f:
+    input r0 as u64.private;
+    input r1 as u64.private;
+    input r2 as boolean.private;
+    add r0 r1 into r3;
+    mul r3 2u64 into r4;
+    sub r0 r1 into r5;
+    ternary r2 r4 r5 into r6;
+    call double r0 into r7;
+    ternary r2 r7 r0 into r8;
+    ternary r2 r0 r1 into r9;
+    lt r6 r8 into r10;
+    ternary r10 r6 r8 into r11;
+    output r11 as u64.private;
+    output r9 as u64.private;
+";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let f = decompile(&a);
+        // The call stays unconditional, the comparison between values computed anyway stays a ternary
+        assert!(f.contains("\tlet r7: u64 = double(r0);\n\tlet r6: u64;\n\tlet r8: u64;\n\tlet r9: u64;\n\tif r2 {\n\t\tr6 = (r0 + r1) * 2u64;\n\t\tr8 = r7;\n\t\tr9 = r0;\n\t} else {\n\t\tr6 = r0 - r1;\n\t\tr8 = r0;\n\t\tr9 = r1;\n\t}\n\treturn (r6 < r8 ? r6 : r8, r9);\n"));
+    }
+
+    #[test]
+    fn rerolls_loops() {
+        let source = "program test.aleo;
+
+mapping counts:
+    key owner as u8.public;
+    value amount as u64.public;
+
+function f:
+    input r0 as u64.private;
+    input r1 as boolean.private;
+    add r0 2u64 into r2;
+    gt r2 10u64 into r3;
+    ternary r3 r2 r0 into r4;
+    add r0 4u64 into r5;
+    gt r5 10u64 into r6;
+    ternary r6 r5 r4 into r7;
+    add r0 6u64 into r8;
+    gt r8 10u64 into r9;
+    ternary r9 r8 r7 into r10;
+    add r0 r10 into r11;
+    output r11 as u64.private;
+    finalize r0;
+
+finalize f:
+    input r0 as u64.public;
+    set r0 into counts[0u8];
+    set r0 into counts[1u8];
+    set r0 into counts[2u8];
+    set r0 into counts[3u8];
+";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let f = decompile(&a);
+        // `r10` is carried from one iteration to the next, the literals follow the counter
+        assert!(f.contains("\tlet r10: u64 = r0;\n\tfor i: u64 in 0u64..3u64 {\n\t\tlet r8: u64 = r0 + (2u64 + 2u64 * i);\n\t\tr10 = r8 > 10u64 ? r8 : r10;\n\t}\n\treturn r0 + r10 then finalize(r0);\n"));
+        assert!(f.contains("\tfor i: u8 in 0u8..4u8 {\n\t\tMapping::set(counts, i, r0);\n\t}\n"));
+        let unrolled = decompile_with(&a, LeoOptions { reroll_loops: false });
+        assert!(!unrolled.contains("for i") && unrolled.contains("\tMapping::set(counts, 3u8, r0);\n"));
+    }
+
+    #[test]
+    fn keeps_overflowing_loops_unrolled() {
+        let decompiled = |literals: [&str; 3]| {
+            let asserts = literals.iter().map(|l| format!("    assert.neq r0 {};\n", l)).collect::<String>();
+            let a = Disassembler::from_aleo(&format!("program test.aleo;\n\nfunction f:\n    input r0 as i8.private;\n{}", asserts)).expect("malformed source");
+            decompile(&a)
+        };
+        // `100i8 - 100i8 * i` would overflow at `i = 2` although every literal fits
+        let f = decompiled(["100i8", "0i8", "-100i8"]);
+        assert!(!f.contains("for i") && f.contains("\tassert_neq(r0, 0i8);\n\tassert_neq(r0, -100i8);\n"), "{}", f);
+        // Stepping by 50 stays in range
+        assert!(decompiled(["100i8", "50i8", "0i8"]).contains("\tfor i: i8 in 0i8..3i8 {\n\t\tassert_neq(r0, 100i8 - 50i8 * i);\n\t}\n"));
+    }
+
+    #[test]
+    fn rerolls_long_bodies_in_linear_time() {
+        extern crate std;
+        use std::time::{Duration, Instant};
+        // Every statement has the same shape, so every period is a candidate, but the literals
+        // follow no counter
+        let asserts = (0..3000u64).map(|k| format!("    assert.neq r0 {}u64;\n", k * k * 7919 % 1_000_003)).collect::<String>();
+        let a = Disassembler::from_aleo(&format!("program test.aleo;\n\nfunction f:\n    input r0 as u64.private;\n{}", asserts)).expect("malformed source");
+        let start = Instant::now();
+        let f = decompile(&a);
+        assert!(start.elapsed() < Duration::from_secs(20), "took {:?}", start.elapsed());
+        assert!(!f.contains("for i") && f.matches("assert_neq").count() == 3000);
+    }
+}
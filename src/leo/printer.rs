@@ -0,0 +1,283 @@
+// Rendering of the Leo syntax tree as source, parenthesizing expressions by precedence
+use core::fmt::Write;
+use super::ast::{BinaryOp, Composite, Expression, Function, FunctionKind, Item, Mapping, Program, Statement, Type, UnaryOp, Visibility};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+fn indent(o: &mut String, depth: usize) {
+    (0..depth).for_each(|_| o.push('\t'));
+}
+
+fn list<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    items.iter().map(f).collect::<Vec<String>>().join(", ")
+}
+
+fn visibility(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Constant => "constant",
+        Visibility::Public => "public",
+        Visibility::Private => "private",
+    }
+}
+
+pub fn ty(t: &Type) -> String {
+    match t {
+        Type::Named(name) => name.clone(),
+        Type::Tuple(types) => format!("({})", list(types, ty)),
+    }
+}
+
+pub fn program(p: &Program) -> String {
+    let mut o = String::new();
+    for import in p.imports.iter() {
+        o.write_fmt(format_args!("import {};\n", import)).unwrap();
+    }
+    if !p.imports.is_empty() {
+        o.push('\n');
+    }
+    o.write_fmt(format_args!("program {} {{\n\n", p.id)).unwrap();
+    // Every item ends with a newline, joining them leaves a blank line in between
+    o.push_str(&p.items.iter().map(item).collect::<Vec<String>>().join("\n"));
+    o.push_str("}\n");
+    o
+}
+
+pub fn item(i: &Item) -> String {
+    match i {
+        Item::Record(r) => composite("record", r),
+        Item::Struct(s) => composite("struct", s),
+        Item::Mapping(m) => mapping(m),
+        Item::Function(f) => function(f),
+    }
+}
+
+fn composite(keyword: &str, c: &Composite) -> String {
+    let mut o = format!("{} {} {{\n", keyword, c.name);
+    for m in c.members.iter() {
+        match m.visibility {
+            Some(v) => o.write_fmt(format_args!("\t{}: {}.{},\n", m.name, ty(&m.ty), visibility(v))).unwrap(),
+            None => o.write_fmt(format_args!("\t{}: {},\n", m.name, ty(&m.ty))).unwrap(),
+        }
+    }
+    o.push_str("}\n");
+    o
+}
+
+fn mapping(m: &Mapping) -> String {
+    format!("mapping {}: {} => {};\n", m.name, ty(&m.key), ty(&m.value))
+}
+
+fn function(f: &Function) -> String {
+    let keyword = match f.kind {
+        FunctionKind::Transition => "transition",
+        FunctionKind::Function => "function",
+        FunctionKind::Finalize => "finalize",
+    };
+    let inputs = list(&f.inputs, |i| match i.visibility {
+        Some(v) => format!("{} {}: {}", visibility(v), i.name, ty(&i.ty)),
+        None => format!("{}: {}", i.name, ty(&i.ty)),
+    });
+    let mut o = format!("{} {}({})", keyword, f.name, inputs);
+    let output = |output: &super::ast::Output| match output.visibility {
+        Some(v) => format!("{} {}", visibility(v), ty(&output.ty)),
+        None => ty(&output.ty),
+    };
+    match f.outputs.len() {
+        0 => {},
+        1 => o.write_fmt(format_args!(" -> {}", output(&f.outputs[0]))).unwrap(),
+        _ => o.write_fmt(format_args!(" -> ({})", list(&f.outputs, output))).unwrap(),
+    }
+    o.push_str(" {\n");
+    block(&mut o, &f.body, 1);
+    o.push_str("}\n");
+    o
+}
+
+fn block(o: &mut String, statements: &[Statement], depth: usize) {
+    for s in statements.iter() {
+        indent(o, depth);
+        write_statement(o, s, depth);
+        o.push('\n');
+    }
+}
+
+// Writes `s` with its terminating `;`, nested blocks one level deeper than `depth`
+fn write_statement(o: &mut String, s: &Statement, depth: usize) {
+    match s {
+        Statement::If { condition, then, otherwise } => {
+            o.write_fmt(format_args!("if {} {{\n", expression(condition))).unwrap();
+            block(o, then, depth + 1);
+            if !otherwise.is_empty() {
+                indent(o, depth);
+                o.push_str("} else {\n");
+                block(o, otherwise, depth + 1);
+            }
+            indent(o, depth);
+            o.push('}');
+        },
+        Statement::For { variable, ty: t, start, end, body } => {
+            o.write_fmt(format_args!("for {}: {} in {}..{} {{\n", variable, ty(t), expression(start), expression(end))).unwrap();
+            block(o, body, depth + 1);
+            indent(o, depth);
+            o.push('}');
+        },
+        _ => o.write_fmt(format_args!("{};", statement(s))).unwrap(),
+    }
+}
+
+// A statement on a single line, without its terminating `;`. An `if` or `for` spans several lines.
+pub fn statement(s: &Statement) -> String {
+    match s {
+        Statement::Assign { targets, ty: declared, value } => {
+            let targets = match targets.len() {
+                1 => targets[0].clone(),
+                _ => format!("({})", targets.join(", ")),
+            };
+            match declared {
+                Some(t) => format!("let {}: {} = {}", targets, ty(t), expression(value)),
+                None => format!("{} = {}", targets, expression(value)),
+            }
+        },
+        Statement::Expression(e) => expression(e),
+        Statement::Return { values, finalize } => {
+            let mut o = String::from("return");
+            match values.len() {
+                0 => {},
+                1 => o.write_fmt(format_args!(" {}", expression(&values[0]))).unwrap(),
+                _ => o.write_fmt(format_args!(" ({})", list(values, expression))).unwrap(),
+            }
+            if let Some(arguments) = finalize {
+                o.write_fmt(format_args!(" then finalize({})", list(arguments, expression))).unwrap();
+            }
+            o
+        },
+        Statement::Declare { name, ty: Some(t) } => format!("let {}: {}", name, ty(t)),
+        Statement::Declare { name, ty: None } => format!("let {}", name),
+        Statement::If { .. } | Statement::For { .. } => {
+            let mut o = String::new();
+            write_statement(&mut o, s, 0);
+            o
+        },
+    }
+}
+
+// Binding strength, higher binds tighter
+const TERNARY: u8 = 1;
+const UNARY: u8 = 11;
+const POSTFIX: u8 = 12;
+const PRIMARY: u8 = 13;
+
+fn binary_precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Eq | BinaryOp::Neq | BinaryOp::Lt | BinaryOp::Lte | BinaryOp::Gt | BinaryOp::Gte => 3,
+        BinaryOp::BitOr => 4,
+        BinaryOp::BitXor => 5,
+        BinaryOp::BitAnd => 6,
+        BinaryOp::Shl | BinaryOp::Shr => 7,
+        BinaryOp::Add | BinaryOp::Sub => 8,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 9,
+        BinaryOp::Pow => 10,
+    }
+}
+
+pub fn binary_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Rem => "%",
+        BinaryOp::Pow => "**",
+        BinaryOp::Shl => "<<",
+        BinaryOp::Shr => ">>",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "^",
+        BinaryOp::Eq => "==",
+        BinaryOp::Neq => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Lte => "<=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Gte => ">=",
+    }
+}
+
+pub fn unary_operator(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Negate => "-",
+        UnaryOp::Not => "!",
+    }
+}
+
+fn precedence(e: &Expression) -> u8 {
+    match e {
+        // Negative literals bind like a negation, e.g. `(-1i8).abs()`
+        Expression::Literal(l) if l.starts_with('-') => UNARY,
+        Expression::Identifier(_) | Expression::Literal(_) | Expression::Call { .. } | Expression::Composite { .. } => PRIMARY,
+        Expression::Member { .. } | Expression::Method { .. } => POSTFIX,
+        Expression::Unary { .. } => UNARY,
+        Expression::Binary { op, .. } => binary_precedence(*op),
+        Expression::Ternary { .. } => TERNARY,
+    }
+}
+
+pub fn expression(e: &Expression) -> String {
+    let mut o = String::new();
+    write_expression(&mut o, e, 0);
+    o
+}
+
+// Writes `e`, in parentheses if it binds looser than `min`
+fn write_expression(o: &mut String, e: &Expression, min: u8) {
+    let parenthesize = precedence(e) < min;
+    if parenthesize {
+        o.push('(');
+    }
+    match e {
+        Expression::Identifier(name) | Expression::Literal(name) => o.push_str(name),
+        Expression::Member { inner, name } => {
+            write_expression(o, inner, POSTFIX);
+            o.write_fmt(format_args!(".{}", name)).unwrap();
+        },
+        Expression::Unary { op, operand } => {
+            o.push_str(unary_operator(*op));
+            write_expression(o, operand, POSTFIX);
+        },
+        Expression::Binary { op, left, right } => {
+            let p = binary_precedence(*op);
+            // Comparisons don't chain, `**` is right associative and the rest left associative
+            let (left_min, right_min) = match op {
+                BinaryOp::Pow => (p + 1, p),
+                _ if p == 3 => (p + 1, p + 1),
+                _ => (p, p + 1),
+            };
+            write_expression(o, left, left_min);
+            o.write_fmt(format_args!(" {} ", binary_operator(*op))).unwrap();
+            write_expression(o, right, right_min);
+        },
+        Expression::Ternary { condition, if_true, if_false } => {
+            write_expression(o, condition, TERNARY + 1);
+            o.push_str(" ? ");
+            write_expression(o, if_true, TERNARY + 1);
+            o.push_str(" : ");
+            write_expression(o, if_false, TERNARY + 1);
+        },
+        Expression::Method { receiver, name, arguments } => {
+            write_expression(o, receiver, POSTFIX);
+            o.write_fmt(format_args!(".{}({})", name, list(arguments, expression))).unwrap();
+        },
+        Expression::Call { function, arguments } => {
+            o.write_fmt(format_args!("{}({})", function, list(arguments, expression))).unwrap();
+        },
+        Expression::Composite { name, members } => {
+            let members = list(members, |(member, value)| match member {
+                Some(member) => format!("{}: {}", member, expression(value)),
+                None => expression(value),
+            });
+            o.write_fmt(format_args!("{} {{ {} }}", name, members)).unwrap();
+        },
+    }
+    if parenthesize {
+        o.push(')');
+    }
+}
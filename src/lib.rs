@@ -1,80 +1,463 @@
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+// The cdylib built for wasm-pack needs the allocator and panic handler of the standard library,
+// which only `std` or the `wasm` bindings bring in
+#[cfg(not(any(feature = "std", feature = "wasm")))]
+compile_error!("aleopath needs either the `std` or the `wasm` feature");
+
+#[cfg(feature = "wasm")]
 #[macro_use]
 mod macros;
+#[cfg(feature = "wasm")]
+mod wasm;
 mod bytecode;
+mod assembler;
+mod error;
+pub mod bech32;
 mod disassembler;
 mod util;
-mod components;
+pub mod components;
 mod output;
+mod leo;
+pub mod visitor;
+pub mod inference;
+#[cfg(feature = "serde")]
+mod schema;
 #[macro_use]
 extern crate alloc;
-use alloc::{vec::Vec, string::String};
 pub use disassembler::Disassembler;
 pub use bytecode::ByteCode;
-use output::Assembly;
-use wasm_bindgen::prelude::*;
-
-extern crate wee_alloc;
-
-// Use `wee_alloc` as the global allocator.
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-#[wasm_bindgen]
-pub fn disassemble(bytes: &str) -> String {
-    let v: Vec<u8> = (0..bytes.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&bytes[i..i + 2], 16).unwrap())
-        .collect();
-    let mut a = Disassembler::from_bytes(v);
-    a.disassemble();
-    a.assembly()
-}
+pub use error::{DecodeError, EncodeError, Location, ParseError};
+pub use output::Assembly;
+pub use leo::LeoOptions;
+pub use components::{Component, Function, Mapping, Struct, Record};
+#[cfg(feature = "wasm")]
+pub use wasm::{disassemble, decompile, abi, assemble, decompile_source};
+#[cfg(feature = "serde")]
+pub use schema::SCHEMA_VERSION;
 
-#[wasm_bindgen]
-pub fn decompile(bytes: &str) -> String {
-    let v: Vec<u8> = (0..bytes.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&bytes[i..i + 2], 16).unwrap())
-        .collect();
-    let mut a = Disassembler::from_bytes(v);
-    a.disassemble();
-    a.leo()
-}
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::{fs, string::String, vec::Vec};
+    use crate::{Disassembler, output::Assembly, error::{DecodeError, Location}};
+    use crate::components::{types::{Attribute, Literal, Type, escape_string}, instructions::Operand};
+
+    // Disassembles `examples/<name>/main.avm` and compares it with the `main.aleo` it was built from
+    fn golden(name: &str) {
+        let bytes = fs::read(format!("examples/{}/main.avm", name)).expect("couldn't read bytecode");
+        let expected = fs::read_to_string(format!("examples/{}/main.aleo", name)).expect("couldn't read source");
+        // Comments do not survive compilation
+        let expected = expected.lines()
+            .filter(|l| !l.trim_start().starts_with("//"))
+            .map(|l| format!("{}\n", l))
+            .collect::<String>();
+        let mut a = Disassembler::from_bytes(bytes);
+        a.disassemble().expect("malformed bytecode");
+        assert_eq!(a.assembly(), expected);
+    }
+
+    // read -> write -> read gives back the same bytes and the same program
+    fn round_trip(name: &str) {
+        let bytes = fs::read(format!("examples/{}/main.avm", name)).expect("couldn't read bytecode");
+        let mut a = Disassembler::from_bytes(bytes.clone());
+        a.disassemble().expect("malformed bytecode");
+        let written = a.to_bytes().expect("couldn't encode");
+        assert_eq!(written, bytes);
+        let mut b = Disassembler::from_bytes(written);
+        b.disassemble().expect("malformed bytecode");
+        assert_eq!(b.assembly(), a.assembly());
+    }
+
+    #[test]
+    fn function() {
+        golden("function");
+    }
+
+    #[test]
+    fn mapping() {
+        golden("mapping");
+    }
+
+    #[test]
+    fn all_components() {
+        golden("all_components");
+    }
+
+    #[test]
+    fn function_round_trip() {
+        round_trip("function");
+    }
+
+    #[test]
+    fn mapping_round_trip() {
+        round_trip("mapping");
+    }
+
+    #[test]
+    fn all_components_round_trip() {
+        round_trip("all_components");
+    }
+
+    // Assembling `examples/<name>/main.aleo` gives back `main.avm`
+    fn assemble(name: &str) {
+        let source = fs::read_to_string(format!("examples/{}/main.aleo", name)).expect("couldn't read source");
+        let bytes = fs::read(format!("examples/{}/main.avm", name)).expect("couldn't read bytecode");
+        let a = Disassembler::from_aleo(&source).expect("malformed source");
+        assert_eq!(a.to_bytes(), Ok(bytes));
+    }
+
+    #[test]
+    fn function_assemble() {
+        assemble("function");
+    }
+
+    #[test]
+    fn mapping_assemble() {
+        assemble("mapping");
+    }
+
+    #[test]
+    fn all_components_assemble() {
+        assemble("all_components");
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let source = "program test.aleo;\n\nfunction hello:\n    input r0 as u32.public;\n    add r0 into r1;\n";
+        let e = Disassembler::from_aleo(source).unwrap_err();
+        assert_eq!((e.line, e.column), (5, 5));
+        assert_eq!(e.message, "`add` takes 2 operands");
+    }
+
+    #[test]
+    fn abi() {
+        let bytes = fs::read("examples/all_components/main.avm").expect("couldn't read bytecode");
+        let expected = fs::read_to_string("examples/all_components/abi.json").expect("couldn't read abi");
+        let mut a = Disassembler::from_bytes(bytes);
+        a.disassemble().expect("malformed bytecode");
+        assert_eq!(a.abi(), expected.trim_end());
+    }
 
-// #[cfg(test)]
-// mod tests {
-//     use std::fs;
-//     use crate::{Disassembler, output::Assembly};
-
-//     // #[test]
-//     // fn function() {
-//     //     let file = "examples/function/main.avm";
-//     //     let file_contents = fs::read(file).expect("couldn't read file");
-//     //     println!("length: {}", file_contents.len());
-//     //     let mut a = Disassembler::from_bytes(file_contents);
-//     //     a.disassemble();
-//     //     println!("{}", a.assembly());
-//     // }
-
-//     // #[test]
-//     // fn mapping() {
-//     //     let file = "examples/mapping/main.avm";
-//     //     let file_contents = fs::read(file).expect("couldn't read file");
-//     //     println!("length: {}", file_contents.len());
-//     //     let mut a = Disassembler::from_bytes(file_contents);
-//     //     a.disassemble();
-//     //     println!("{}", a.assembly());
-//     // }
-
-//     #[test]
-//     fn all_components() {
-//         let file = "examples/all_components/main.avm";
-//         let file_contents = fs::read(file).expect("couldn't read file");
-//         println!("length: {}", file_contents.len());
-//         let mut a = Disassembler::from_bytes(file_contents);
-//         a.disassemble();
-//         println!("{}", a.leo());
-//     }
-// }
\ No newline at end of file
+    #[test]
+    fn navigates_model() {
+        use crate::components::instructions::Opcode;
+        let bytes = fs::read("examples/all_components/main.avm").expect("couldn't read bytecode");
+        let mut a = Disassembler::from_bytes(bytes);
+        a.disassemble().expect("malformed bytecode");
+        assert_eq!(a.functions().map(|f| f.name()).collect::<std::vec::Vec<_>>(), ["sum_one_to_array3", "hello"]);
+        assert_eq!(a.structure("array3").and_then(|s| s.member("a1")), Some(&Type::U32));
+        assert_eq!(a.mapping("account").map(|m| m.key().value_type()), Some(&Type::Address));
+        assert_eq!(a.record("token").map(|r| *r.owner()), Some(Attribute::Private));
+        let hello = a.function("hello").expect("missing function");
+        assert_eq!(hello.inputs()[0].attribute(), Some(&Attribute::Public));
+        let xor = &hello.instructions()[0];
+        assert_eq!(xor.opcode(), Opcode::Xor);
+        assert_eq!(xor.opcode().leo(), "^");
+        assert!(matches!(&xor.operands()[1], Operand::Register(r) if r.locator() == 1));
+        assert_eq!(xor.destinations()[0].locator(), 5);
+        assert_eq!(xor.operands().to_vec().leo(), "r0, r1");
+        assert_eq!(a.component(1).map(|c| c.kind()), Some("mapping"));
+    }
+
+    // Identifier as encoded in bytecode: its length, then its bytes
+    fn id(name: &str) -> Vec<u8> {
+        let mut b = vec![name.len() as u8];
+        b.extend_from_slice(name.as_bytes());
+        b
+    }
+
+    // Decodes `test.aleo` importing `imports`, built from already encoded components
+    fn handcrafted(imports: &[&str], components: &[Vec<u8>]) -> Disassembler {
+        let b = program(imports, components);
+        let mut a = Disassembler::from_bytes(b.clone());
+        a.disassemble().expect("malformed bytecode");
+        assert_eq!(a.to_bytes(), Ok(b));
+        a
+    }
+
+    // Bytecode of `test.aleo` with the given imports and components, components starting at offset 15
+    // when there are no imports
+    fn program(imports: &[&str], components: &[Vec<u8>]) -> Vec<u8> {
+        let mut b = vec![0, 0];
+        b.extend(id("test"));
+        b.extend(id("aleo"));
+        b.push(imports.len() as u8);
+        for import in imports {
+            b.extend(id(import));
+            b.extend(id("aleo"));
+        }
+        b.extend_from_slice(&(components.len() as u16).to_le_bytes());
+        components.iter().for_each(|c| b.extend_from_slice(c));
+        b
+    }
+
+    // Error of decoding a program made of one function `f` taking r0 as u64.public, whose single
+    // instruction is the given bytes
+    fn decode_error(instruction: &[u8]) -> DecodeError {
+        let function = component(&[&[4], &id("f"), &[1, 0, 0, 0, 1, 0, 12, 0], &[1, 0, 0, 0], instruction]);
+        Disassembler::from_bytes(program(&[], &[function])).disassemble().unwrap_err()
+    }
+
+    #[test]
+    fn decode_errors() {
+        // The function's instruction starts at offset 15 + 1 + 2 + 6 + 4
+        assert_eq!(decode_error(&[]), DecodeError::UnexpectedEof(Location { offset: 30, context: "f".into() }));
+        // add r0 1u64 cut after four bytes of the literal, reported at the end of the bytecode
+        assert!(matches!(decode_error(&[2, 0, 1, 0, 0, 0, 12, 0, 1, 0, 0, 0]), DecodeError::UnexpectedEof(l) if l.offset == 42));
+        assert!(matches!(decode_error(&[200, 0]), DecodeError::UnknownOpcode(200, l) if l.offset == 30));
+        assert!(matches!(decode_error(&[2, 0, 1, 0, 0, 0, 99, 0]), DecodeError::UnknownLiteralType(99, l) if l.offset == 36));
+        assert!(matches!(decode_error(&[7, 0, 2]), DecodeError::UnknownCallVariant(2, l) if l.offset == 32));
+        // add r0 with a boolean literal of value 2
+        assert!(matches!(decode_error(&[2, 0, 1, 0, 0, 0, 1, 0, 2]), DecodeError::InvalidBoolean(2, l) if l.offset == 38));
+        // A component name cut after two of its five bytes
+        let truncated = program(&[], &[vec![4, 5, b'h', b'e']]);
+        assert!(matches!(Disassembler::from_bytes(truncated).disassemble(), Err(DecodeError::UnexpectedEof(l)) if l.offset == 19));
+    }
+
+    // Bytecode of a component: its parts in order, identifiers already length-prefixed by `id`
+    fn component(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    // Decoding and rendering of hand-laid bytecode: the case, the imports, the components, the
+    // expected assembly and what else the case checks on the decoded program
+    #[allow(clippy::type_complexity)]
+    fn handcrafted_cases() -> Vec<(&'static str, &'static [&'static str], Vec<Vec<u8>>, &'static str, fn(&Disassembler))> {
+        let u64_closure = component(&[&[3], &id("c"), &[1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 12, 0]]);
+        // function f: call other.aleo/f r0; finalize; finalize f: call another.aleo/g;
+        let external_calls = component(&[
+            &[4], &id("f"), &[1, 0, 0, 0, 1, 0, 12, 0, 1, 0, 0, 0, 7, 0, 0], &id("other"), &id("aleo"), &id("f"),
+            &[1, 1, 0, 0, 0, 0, 0, 1, 0], &id("f"), &[0, 0, 1, 0, 0, 7, 0, 0], &id("another"), &id("aleo"), &id("g"), &[0, 0],
+        ]);
+        vec![
+            ("closure", &[], vec![component(&[
+                // closure helper: input r0 as u8; add r0 r0 into r1; output r1 as u8;
+                &[3], &id("helper"), &[1, 0, 0, 0, 0, 0, 9, 0],
+                &[1, 0, 0, 0, 2, 0, 1, 0, 0, 1, 0, 0, 0, 1],
+                &[1, 0, 0, 1, 0, 0, 9, 0],
+            ])], "program test.aleo;\n\nclosure helper:\n    input r0 as u8;\n    add r0 r0 into r1;\n    output r1 as u8;\n", |a| {
+                let helper = a.function("helper").expect("missing closure");
+                assert_eq!(helper.function_type(), crate::components::function::FunctionType::Closure);
+                assert_eq!(helper.inputs()[0].attribute(), None);
+                assert!(a.leo().contains("function helper(r0: u8) -> u8 {"));
+            }),
+            ("finalize", &[], vec![component(&[
+                // function f: input r0 as u64.public; finalize r0;
+                &[4], &id("f"), &[1, 0, 0, 0, 1, 0, 12, 0, 0, 0, 0, 0, 0, 0], &[1, 1, 1, 0, 0],
+                // finalize f: input r0 as u64.public; add r0 1u64 into r1;
+                &id("f"), &[1, 0, 0, 0, 0, 12, 0],
+                &[1, 0, 0, 2, 0, 1, 0, 0, 0, 12, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            ])], "program test.aleo;\n\nfunction f:\n    input r0 as u64.public;\n    finalize r0;\n\n\
+                finalize f:\n    input r0 as u64.public;\n    add r0 1u64 into r1;\n", |a| {
+                let f = a.function("f").expect("missing function");
+                assert_eq!(f.finalize_operands().map(|o| o.len()), Some(1));
+                assert_eq!(f.finalize().map(|f| f.commands().len()), Some(1));
+                let leo = a.leo();
+                assert!(leo.contains("return then finalize(r0);"));
+                assert!(leo.contains("finalize f(r0: u64) {"));
+            }),
+            ("record types", &["credits"], vec![
+                // function g: input r0 as token.record; input r1 as credits.aleo/credits.record; output r0 as token.record;
+                component(&[
+                    &[4], &id("g"), &[2, 0, 0, 0, 3], &id("token"), &[0, 1, 4], &id("credits"), &id("aleo"), &id("credits"),
+                    &[0, 0, 0, 0, 1, 0, 0, 0, 3], &id("token"), &[0],
+                ]),
+                // closure h: input r0 as token.record; input r1 as credits.aleo/credits.record;
+                component(&[
+                    &[3], &id("h"), &[2, 0, 0, 0, 1], &id("token"), &[0, 1, 2], &id("credits"), &id("aleo"), &id("credits"),
+                    &[0, 0, 0, 0, 0, 0],
+                ]),
+            ], "import credits.aleo;\n\nprogram test.aleo;\n\n\
+                function g:\n    input r0 as token.record;\n    input r1 as credits.aleo/credits.record;\n    output r0 as token.record;\n\n\
+                closure h:\n    input r0 as token.record;\n    input r1 as credits.aleo/credits.record;\n", |a| {
+                let g = a.function("g").expect("missing function");
+                assert_eq!(g.inputs()[0].value_type(), &Type::Record("token".into()));
+                assert_eq!(g.inputs()[1].value_type(), &Type::ExternalRecord(("credits".into(), "aleo".into(), "credits".into())));
+                assert_eq!(g.inputs()[1].attribute(), Some(&Attribute::ExternalRecord));
+                assert!(a.leo().contains("transition g(r0: token, r1: credits.aleo/credits) -> token {"));
+            }),
+            ("group and string literals", &[], vec![component(&[
+                // closure l: input r0 as group; add r0 2group into r1; is.eq "say \"hi\"\n" r0 into r2; output r1 as group;
+                &[3], &id("l"), &[1, 0, 0, 0, 0, 0, 3, 0],
+                &[2, 0, 0, 0, 2, 0, 1, 0, 0, 0, 3, 0, 2], &[0; 31],
+                &[0, 1, 30, 0, 0, 15, 0, 9, 0], b"say \"hi\"\n", &[1, 0, 0, 0, 2],
+                &[1, 0, 0, 1, 0, 0, 3, 0],
+            ])], "program test.aleo;\n\nclosure l:\n    input r0 as group;\n    add r0 2group into r1;\n    \
+                is.eq \"say \\\"hi\\\"\\n\" r0 into r2;\n    output r1 as group;\n", |a| {
+                let l = a.function("l").expect("missing closure");
+                assert_eq!(l.instructions()[0].operands()[1], Operand::Literal(Literal::Group([&[2][..], &[0; 31]].concat())));
+                assert_eq!(l.instructions()[1].operands()[0], Operand::Literal(Literal::String("say \"hi\"\n".into())));
+                assert_eq!(escape_string("tab\tquote\"back\\nul\0bell\u{7}"), "tab\\tquote\\\"back\\\\nul\\0bell\\u{7}");
+            }),
+            ("unimported calls", &[], vec![external_calls.clone()], "program test.aleo;\n\nfunction f:\n    input r0 as u64.public;\n    \
+                call other.aleo/f r0;\n    finalize;\n\nfinalize f:\n    call another.aleo/g;\n", |a| {
+                assert_eq!(a.warnings(), [
+                    "`f` calls `other.aleo/f` but `other.aleo` is not imported",
+                    "`f` calls `another.aleo/g` but `another.aleo` is not imported",
+                ]);
+            }),
+            ("imported calls", &["other", "another"], vec![external_calls], "import other.aleo;\nimport another.aleo;\n\n\
+                program test.aleo;\n\nfunction f:\n    input r0 as u64.public;\n    call other.aleo/f r0;\n    finalize;\n\n\
+                finalize f:\n    call another.aleo/g;\n", |a| {
+                assert!(a.warnings().is_empty());
+            }),
+            ("call and cast", &[], vec![
+                // record token: owner as address.private; gates as u64.private;
+                component(&[&[2], &id("token"), &[1, 1, 0, 0]]),
+                // closure c: input r0 as u64; output r0 as u64;
+                u64_closure,
+                component(&[
+                    // function f: input r0 as u64.private; input r1 as address.private;
+                    &[4], &id("f"), &[2, 0, 0, 0, 2, 0, 12, 0, 0, 1, 2, 0, 0, 0, 2, 0, 0, 0],
+                    // call c r0 into r2;
+                    &[7, 0, 1], &id("c"), &[1, 1, 0, 0, 1, 0, 2],
+                    // cast r1 r2 into r3 as token.record;
+                    &[8, 0, 2, 1, 0, 1, 1, 0, 2, 0, 3, 1], &id("token"),
+                    // output r3 as token.record; no finalize
+                    &[1, 0, 0, 3, 3], &id("token"), &[0],
+                ]),
+            ], "program test.aleo;\n\nrecord token:\n    owner as address.private;\n    gates as u64.private;\n\n\
+                closure c:\n    input r0 as u64;\n    output r0 as u64;\n\nfunction f:\n    input r0 as u64.private;\n    \
+                input r1 as address.private;\n    call c r0 into r2;\n    cast r1 r2 into r3 as token.record;\n    \
+                output r3 as token.record;\n", |a| {
+                let f = a.function("f").expect("missing function");
+                assert_eq!(f.instructions()[0].callee(), Some(&crate::components::instructions::Locator::Internal("c".into())));
+                assert_eq!(Disassembler::from_aleo(&a.assembly()).map(|b| b.to_bytes()), Ok(a.to_bytes()));
+            }),
+        ]
+    }
+
+    #[test]
+    fn handcrafted_programs() {
+        for (case, imports, components, assembly, check) in handcrafted_cases() {
+            let a = handcrafted(imports, &components);
+            assert_eq!(a.assembly(), assembly, "{}", case);
+            check(&a);
+        }
+    }
+
+    // `account` mapping from addresses to u64, then `function f: input r0 as address.public;
+    // finalize r0;` whose finalize block runs `commands` on it. The commands are laid out the way
+    // snarkVM's `Command::write_le` writes them: tag, mapping name, key, then the other operands.
+    fn mapping_program(commands: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut m = vec![0];
+        m.extend(id("account"));
+        m.extend(id("owner"));
+        m.extend_from_slice(&[0, 0, 0, 0]);
+        m.extend(id("amount"));
+        m.extend_from_slice(&[0, 0, 12, 0]);
+        let mut c = vec![4];
+        c.extend(id("f"));
+        c.extend_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0]);
+        c.extend(id("f"));
+        c.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0]);
+        c.extend_from_slice(&(commands.len() as u16).to_le_bytes());
+        commands.iter().for_each(|command| c.extend_from_slice(command));
+        vec![m, c]
+    }
+
+    // `tag account[r0]` followed by `rest`
+    fn command(tag: u8, rest: &[u8]) -> Vec<u8> {
+        let mut b = vec![tag];
+        b.extend(id("account"));
+        b.extend_from_slice(&[1, 0, 0]);
+        b.extend_from_slice(rest);
+        b
+    }
+
+    #[test]
+    fn command_layouts() {
+        use crate::components::commands::CommandLayout;
+        let zero = [0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let header = "program test.aleo;\n\nmapping account:\n    key owner as address.public;\n    value amount as u64.public;\n\n\
+            function f:\n    input r0 as address.public;\n    finalize r0;\n\nfinalize f:\n    input r0 as address.public;\n";
+
+        // 1 contains, 2 get, 3 get.or_use, 5 remove, 6 set
+        let current = mapping_program(&[
+            command(1, &[0, 1]),
+            command(2, &[0, 2]),
+            command(3, &[&zero[..], &[0, 3]].concat()),
+            command(6, &[1, 0, 3]),
+            command(5, &[]),
+        ]);
+        let a = handcrafted(&[], &current);
+        assert_eq!(a.function("f").and_then(|f| f.finalize()).map(|f| f.layout()), Some(CommandLayout::Current));
+        assert_eq!(a.assembly(), format!("{}{}", header, "    contains account[r0] into r1;\n    get account[r0] into r2;\n    \
+            get.or_use account[r0] 0u64 into r3;\n    set r3 into account[r0];\n    remove account[r0];\n"));
+
+        // 1 decrement, 2 get, 3 get.or_init, 4 increment, 5 set
+        let legacy = mapping_program(&[
+            command(3, &[&zero[..], &[0, 1]].concat()),
+            command(4, &[1, 0, 1]),
+            command(1, &[1, 0, 1]),
+            command(2, &[0, 2]),
+            command(5, &[1, 0, 2]),
+        ]);
+        let a = handcrafted(&[], &legacy);
+        assert_eq!(a.function("f").and_then(|f| f.finalize()).map(|f| f.layout()), Some(CommandLayout::Legacy));
+        let source = format!("{}{}", header, "    get.or_init account[r0] 0u64 into r1;\n    increment account[r0] by r1;\n    \
+            decrement account[r0] by r1;\n    get account[r0] into r2;\n    set r2 into account[r0];\n");
+        assert_eq!(a.assembly(), source);
+        assert_eq!(Disassembler::from_aleo(&source).expect("malformed source").to_bytes(), a.to_bytes());
+
+        // rand.chacha is well formed but not decoded, tag 7 does not exist in either layout
+        for (tag, expected) in [(4, "unsupported rand.chacha command"), (7, "unknown command tag 7")] {
+            let mut b = vec![0, 0];
+            b.extend(id("test"));
+            b.extend(id("aleo"));
+            b.extend_from_slice(&[0, 2, 0]);
+            mapping_program(&[command(tag, &[0, 1])]).iter().for_each(|c| b.extend_from_slice(c));
+            let error = Disassembler::from_bytes(b).disassemble().expect_err("decoded an unknown command");
+            assert!(format!("{}", error).starts_with(expected), "{}", error);
+        }
+
+        // The two layouts cannot share a block
+        let mixed = source.replace("decrement account[r0] by r1;", "remove account[r0];");
+        assert!(Disassembler::from_aleo(&mixed).is_err());
+    }
+
+    #[test]
+    fn program_id_operand() {
+        let source = "program test.aleo;\n\nfunction f:\n    input r0 as address.public;\n    is.eq credits.aleo r0 into r1;\n    output r1 as boolean.public;\n";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        assert_eq!(a.assembly(), source);
+        // Operand tag 2 followed by the program id, without a resource
+        let mut operand = vec![2];
+        operand.extend(id("credits"));
+        operand.extend(id("aleo"));
+        let bytes = a.to_bytes().expect("couldn't encode");
+        assert!(bytes.windows(operand.len()).any(|w| w == operand));
+        let mut b = Disassembler::from_bytes(bytes);
+        b.disassemble().expect("malformed bytecode");
+        assert_eq!(b.assembly(), source);
+    }
+
+    #[test]
+    fn rejects_unencodable_models() {
+        use crate::EncodeError;
+        use crate::components::{Component, types::write_plaintext_type, instructions::Locator};
+        let source = "program test.aleo;\n\nclosure c:\n    input r0 as u64;\n    output r0 as u64;\n\n\
+            function f:\n    input r0 as u64.private;\n    call c r0 into r1;\n    add r0 r1 into r2;\n    output r2 as u64.private;\n";
+        let a = Disassembler::from_aleo(source).expect("malformed source");
+        let edited = |edit: &dyn Fn(&mut Vec<Operand>, &mut Vec<Operand>)| {
+            let mut b = Disassembler::from_aleo(source).expect("malformed source");
+            if let Some(Component::Function(f)) = b.components_mut().get_mut(1) {
+                let (call, add) = f.instructions_mut().split_at_mut(1);
+                edit(call[0].operands_mut(), add[0].operands_mut());
+            }
+            b.to_bytes()
+        };
+        assert!(a.to_bytes().is_ok());
+        assert!(matches!(edited(&|call, _| { call.remove(0); }), Err(EncodeError::InvalidInstruction(i)) if i == "call r0 into r1;"));
+        assert!(matches!(edited(&|_, add| { add.pop(); }), Err(EncodeError::InvalidInstruction(i)) if i == "add r0 into r2;"));
+        let long = "c".repeat(256);
+        assert_eq!(edited(&|call, _| call[0] = Operand::ProgramId(Locator::Internal(long.clone()))), Err(EncodeError::IdentifierTooLong(long.clone())));
+        assert_eq!(edited(&|_, add| add[1] = Operand::Literal(Literal::Field(vec![1; 3]))), Err(EncodeError::InvalidLiteralLength(3)));
+        assert_eq!(edited(&|_, add| add[1] = Operand::ProgramId(Locator::Internal("c".into()))), Err(EncodeError::InvalidProgramId("c".into())));
+        assert_eq!(write_plaintext_type(&mut Vec::new(), &Type::Record("token".into())), Err(EncodeError::NotPlaintext("token.record".into())));
+    }
+}
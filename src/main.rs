@@ -0,0 +1,223 @@
+// Command line front end to the disassembler
+use std::{env, fs, io::{self, Read, Write}, path::Path, process::ExitCode};
+use aleopath::{Assembly, Disassembler, LeoOptions};
+
+const USAGE: &str = "usage: aleopath <command> [input] [-o <output>] [--no-loops]
+
+commands:
+    disassemble    print the program as Aleo instructions
+    decompile      print the program as Leo
+    header         print the program id, version, imports and components
+    abi            print the functions, records, structs and mappings callers depend on, as JSON
+
+input is an .avm or .aleo file, a hex string, or `-` / nothing to read stdin
+output is written to stdout unless a file is given with -o
+--no-loops keeps unrolled loops unrolled when decompiling";
+
+enum Command {
+    Disassemble,
+    Decompile,
+    Header,
+    Abi,
+}
+
+struct Args {
+    command: Command,
+    input: Option<String>,
+    output: Option<String>,
+    leo: LeoOptions,
+}
+
+// Failures are reported with their exit code: 2 for usage errors, 1 for everything else
+struct Error(u8, String);
+
+impl Error {
+    fn usage(message: String) -> Self {
+        Self(2, format!("{}\n\n{}", message, USAGE))
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, Error> {
+    let command = match args.next().as_deref() {
+        Some("disassemble") => Command::Disassemble,
+        Some("decompile") => Command::Decompile,
+        Some("header") => Command::Header,
+        Some("abi") => Command::Abi,
+        Some(c) => return Err(Error::usage(format!("unknown command `{}`", c))),
+        None => return Err(Error::usage("missing command".to_string())),
+    };
+    let (mut input, mut output, mut leo) = (None, None, LeoOptions::default());
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(o) => output = Some(o),
+                None => return Err(Error::usage(format!("missing file after `{}`", arg))),
+            },
+            "--no-loops" => leo.reroll_loops = false,
+            a if a.starts_with('-') && a != "-" => return Err(Error::usage(format!("unknown option `{}`", a))),
+            _ if input.is_some() => return Err(Error::usage(format!("unexpected argument `{}`", arg))),
+            _ => input = Some(arg),
+        }
+    }
+    Ok(Args { command, input, output, leo })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok())).collect()
+}
+
+enum Program {
+    Bytecode(Vec<u8>),
+    Source(String),
+}
+
+// Bytecode always holds NUL bytes, text is either hex encoded bytecode or Aleo instructions
+fn classify(bytes: Vec<u8>) -> Program {
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => match decode_hex(text.trim()) {
+            Some(bytes) => Program::Bytecode(bytes),
+            None => Program::Source(text),
+        },
+        Ok(text) => Program::Bytecode(text.into_bytes()),
+        Err(e) => Program::Bytecode(e.into_bytes()),
+    }
+}
+
+fn read_input(input: Option<&str>) -> Result<Program, Error> {
+    match input {
+        None | Some("-") => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes).map_err(|e| Error(1, format!("couldn't read stdin: {}", e)))?;
+            Ok(classify(bytes))
+        },
+        Some(path) if Path::new(path).is_file() => {
+            let bytes = fs::read(path).map_err(|e| Error(1, format!("couldn't read `{}`: {}", path, e)))?;
+            match Path::new(path).extension().and_then(|e| e.to_str()) {
+                Some("aleo") => String::from_utf8(bytes)
+                    .map(Program::Source)
+                    .map_err(|_| Error(1, format!("`{}` is not valid utf-8", path))),
+                _ => Ok(classify(bytes)),
+            }
+        },
+        Some(hex) => decode_hex(hex)
+            .map(Program::Bytecode)
+            .ok_or_else(|| Error(1, format!("`{}` is neither a file nor a hex string", hex))),
+    }
+}
+
+fn load(program: Program) -> Result<Disassembler, Error> {
+    let a = match program {
+        Program::Bytecode(bytes) => {
+            let mut a = Disassembler::from_bytes(bytes);
+            a.disassemble().map_err(|e| Error(1, format!("failed to decode program: {}", e)))?;
+            a
+        },
+        Program::Source(source) => Disassembler::from_aleo(&source).map_err(|e| Error(1, format!("failed to parse program: {}", e)))?,
+    };
+    for warning in a.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    Ok(a)
+}
+
+fn header(a: &Disassembler) -> String {
+    let (name, network) = a.get_program_id();
+    let mut o = format!("program: {}.{}\nversion: {}\n", name, network, a.get_version());
+    let imports = a.imports().iter().map(|(p, n)| format!("{}.{}", p, n)).collect::<Vec<String>>();
+    match imports.is_empty() {
+        true => o.push_str("imports: none\n"),
+        false => o.push_str(&format!("imports: {}\n", imports.join(", "))),
+    }
+    o.push_str(&format!("components: {}\n", a.components().len()));
+    for c in a.components() {
+        o.push_str(&format!("    {} {}\n", c.kind(), c.name()));
+    }
+    o
+}
+
+fn run() -> Result<(), Error> {
+    let args = parse_args(env::args().skip(1))?;
+    let a = load(read_input(args.input.as_deref())?)?;
+    let text = match args.command {
+        Command::Disassemble => a.assembly(),
+        Command::Decompile => a.leo_with(args.leo),
+        Command::Header => header(&a),
+        Command::Abi => a.abi() + "\n",
+    };
+    match args.output {
+        Some(path) => fs::write(&path, text).map_err(|e| Error(1, format!("couldn't write `{}`: {}", path, e))),
+        None => io::stdout().write_all(text.as_bytes()).map_err(|e| Error(1, format!("couldn't write output: {}", e))),
+    }
+}
+
+fn main() -> ExitCode {
+    if env::args().skip(1).any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error(code, message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(code)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args, Error> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    // Exit code and first line of a usage error
+    fn usage_error(line: &str) -> (u8, String) {
+        match args(line) {
+            Ok(_) => panic!("`{}` was accepted", line),
+            Err(Error(code, message)) => (code, message.lines().next().unwrap_or_default().to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_args() {
+        let a = args("decompile main.avm -o main.leo --no-loops").unwrap_or_else(|_| panic!("rejected"));
+        assert!(matches!(a.command, Command::Decompile));
+        assert_eq!(a.input.as_deref(), Some("main.avm"));
+        assert_eq!(a.output.as_deref(), Some("main.leo"));
+        assert!(!a.leo.reroll_loops);
+        let a = args("abi -").unwrap_or_else(|_| panic!("rejected"));
+        assert!(matches!(a.command, Command::Abi));
+        assert_eq!(a.input.as_deref(), Some("-"));
+        assert!(a.output.is_none() && a.leo.reroll_loops);
+        let a = args("header --output out.txt").unwrap_or_else(|_| panic!("rejected"));
+        assert!(matches!(a.command, Command::Header) && a.input.is_none());
+        assert_eq!(a.output.as_deref(), Some("out.txt"));
+    }
+
+    #[test]
+    fn rejects_bad_args() {
+        assert_eq!(usage_error(""), (2, "missing command".to_string()));
+        assert_eq!(usage_error("compile main.avm"), (2, "unknown command `compile`".to_string()));
+        assert_eq!(usage_error("disassemble main.avm -o"), (2, "missing file after `-o`".to_string()));
+        assert_eq!(usage_error("disassemble --loops"), (2, "unknown option `--loops`".to_string()));
+        assert_eq!(usage_error("disassemble a.avm b.avm"), (2, "unexpected argument `b.avm`".to_string()));
+    }
+
+    #[test]
+    fn classifies_input() {
+        assert!(matches!(classify(b"0x0000ff".to_vec()), Program::Bytecode(b) if b == [0, 0, 0xff]));
+        assert!(matches!(classify(b" 0000ff\n".to_vec()), Program::Bytecode(b) if b == [0, 0, 0xff]));
+        // Odd length and non-hex text is source
+        assert!(matches!(classify(b"000".to_vec()), Program::Source(s) if s == "000"));
+        assert!(matches!(classify(b"program test.aleo;\n".to_vec()), Program::Source(s) if s == "program test.aleo;\n"));
+        // Raw bytecode, valid utf-8 or not
+        assert!(matches!(classify(vec![0, 0, 4, b't']), Program::Bytecode(b) if b == [0, 0, 4, b't']));
+        assert!(matches!(classify(vec![0xff, 0, 1]), Program::Bytecode(b) if b == [0xff, 0, 1]));
+    }
+}
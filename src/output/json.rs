@@ -0,0 +1,86 @@
+use core::fmt::Write;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+// Minimal JSON document, enough to describe a program without pulling in a serializer.
+// Object keys keep their insertion order so the output is stable across runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+fn write_string(o: &mut String, s: &str) {
+    o.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => o.push_str("\\\""),
+            '\\' => o.push_str("\\\\"),
+            '\n' => o.push_str("\\n"),
+            '\r' => o.push_str("\\r"),
+            '\t' => o.push_str("\\t"),
+            c if (c as u32) < 0x20 => o.write_fmt(format_args!("\\u{:04x}", c as u32)).unwrap(),
+            c => o.push(c),
+        }
+    }
+    o.push('"');
+}
+
+impl Json {
+    // Two-space indented rendering, empty arrays and objects stay on one line
+    pub fn pretty(&self) -> String {
+        let mut o = String::new();
+        self.write(&mut o, 0);
+        o
+    }
+
+    fn write(&self, o: &mut String, depth: usize) {
+        let indent = |o: &mut String, depth: usize| (0..depth).for_each(|_| o.push_str("  "));
+        match self {
+            Self::Null => o.push_str("null"),
+            Self::Number(n) => o.write_fmt(format_args!("{}", n)).unwrap(),
+            Self::String(s) => write_string(o, s),
+            Self::Array(items) if items.is_empty() => o.push_str("[]"),
+            Self::Object(fields) if fields.is_empty() => o.push_str("{}"),
+            Self::Array(items) => {
+                o.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    o.push_str(if i == 0 { "\n" } else { ",\n" });
+                    indent(o, depth + 1);
+                    item.write(o, depth + 1);
+                }
+                o.push('\n');
+                indent(o, depth);
+                o.push(']');
+            },
+            Self::Object(fields) => {
+                o.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    o.push_str(if i == 0 { "\n" } else { ",\n" });
+                    indent(o, depth + 1);
+                    write_string(o, key);
+                    o.push_str(": ");
+                    value.write(o, depth + 1);
+                }
+                o.push('\n');
+                indent(o, depth);
+                o.push('}');
+            },
+        }
+    }
+}
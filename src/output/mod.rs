@@ -1,6 +1,9 @@
+pub mod json;
+
 use alloc::string::String;
 
-pub(crate) trait Assembly {
+// Rendering of the program and its parts as Aleo instructions and as Leo
+pub trait Assembly {
     fn assembly(&self) -> String;
     fn leo(&self) -> String;
 }
\ No newline at end of file
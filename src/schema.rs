@@ -0,0 +1,122 @@
+// Serde representation of a decoded program, versioned so stored models can be told apart
+// from ones written by an incompatible release. Components keep serde's default externally
+// tagged layout, e.g. `{ "Function": { .. } }`, the program itself is wrapped as
+// `{ "schema", "version", "program", "network", "imports", "components", "warnings" }`.
+use crate::{Assembly, Disassembler};
+use crate::components::Component;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+// Bumped whenever the serialized layout of the program or any component changes
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct ProgramRef<'a> {
+    schema: u32,
+    version: u16,
+    program: &'a str,
+    network: &'a str,
+    imports: &'a [(String, String)],
+    components: &'a [Component],
+    warnings: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct Program {
+    schema: u32,
+    version: u16,
+    program: String,
+    network: String,
+    imports: Vec<(String, String)>,
+    components: Vec<Component>,
+    #[serde(default)]
+    warnings: Vec<String>,
+}
+
+impl Serialize for Disassembler {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (program, network) = self.get_program_id();
+        ProgramRef {
+            schema: SCHEMA_VERSION,
+            version: self.get_version(),
+            program,
+            network,
+            imports: self.imports(),
+            components: self.components(),
+            warnings: self.warnings(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Disassembler {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let p = Program::deserialize(deserializer)?;
+        if p.schema != SCHEMA_VERSION {
+            return Err(D::Error::custom(format!("unsupported schema version {}, expected {}", p.schema, SCHEMA_VERSION)));
+        }
+        let a = Disassembler::from_parts(p.version, (p.program, p.network), p.imports, p.components, p.warnings);
+        // Serde accepts models no bytecode can hold, e.g. a cast into a single register or a
+        // command on a mapping that is not declared, so only keep those that decode back to
+        // the same program
+        let bytes = a.to_bytes().map_err(D::Error::custom)?;
+        let mut decoded = Disassembler::from_bytes(bytes);
+        decoded.disassemble().map_err(D::Error::custom)?;
+        if decoded.assembly() != a.assembly() {
+            return Err(D::Error::custom("model changes when encoded and decoded back"));
+        }
+        Ok(a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::{fs, string::ToString};
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let bytes = fs::read("examples/all_components/main.avm").expect("couldn't read bytecode");
+        let mut a = Disassembler::from_bytes(bytes.clone());
+        a.disassemble().expect("malformed bytecode");
+        let json = serde_json::to_string(&a).expect("couldn't serialize");
+        let b: Disassembler = serde_json::from_str(&json).expect("couldn't deserialize");
+        assert_eq!(b.assembly(), a.assembly());
+        assert_eq!(b.to_bytes(), Ok(bytes));
+        assert_eq!(b.records().count(), 1);
+    }
+
+    #[test]
+    fn rejects_other_schema_versions() {
+        let json = r#"{"schema":0,"version":0,"program":"test","network":"aleo","imports":[],"components":[]}"#;
+        let error = serde_json::from_str::<Disassembler>(json).expect_err("accepted schema 0");
+        assert!(error.to_string().contains("unsupported schema version 0"));
+    }
+
+    #[test]
+    fn rejects_invalid_models() {
+        let source = "program test.aleo;\n\nmapping account:\n    key owner as address.public;\n    value amount as u64.public;\n\n\
+            function f:\n    input r0 as address.public;\n    finalize r0;\n\nfinalize f:\n    input r0 as address.public;\n    get account[r0] into r1;\n";
+        let json = serde_json::to_string(&Disassembler::from_aleo(source).expect("malformed source")).expect("couldn't serialize");
+        assert!(serde_json::from_str::<Disassembler>(&json).is_ok());
+
+        // Encodes, but the finalize block reads a mapping the program does not declare
+        let json_unknown = json.replace(r#""mapping":"account""#, r#""mapping":"other""#);
+        let error = serde_json::from_str::<Disassembler>(&json_unknown).expect_err("accepted an unknown mapping");
+        assert!(error.to_string().contains("unknown mapping `other`"), "{}", error);
+
+        // Does not encode at all
+        let long = "f".repeat(256);
+        let json_long = json.replace(r#""name":"f""#, &format!(r#""name":"{}""#, long));
+        let error = serde_json::from_str::<Disassembler>(&json_long).expect_err("accepted a 256 byte identifier");
+        assert!(error.to_string().contains("longer than 255 bytes"), "{}", error);
+
+        // A record owner can only be public or private
+        let record = "program test.aleo;\n\nrecord token:\n    owner as address.private;\n    gates as u64.private;\n";
+        let json_record = serde_json::to_string(&Disassembler::from_aleo(record).expect("malformed source")).expect("couldn't serialize");
+        let json_constant = json_record.replace(r#""owner_attribute":"Private""#, r#""owner_attribute":"Constant""#);
+        assert_ne!(json_constant, json_record);
+        assert!(serde_json::from_str::<Disassembler>(&json_constant).is_err());
+    }
+}
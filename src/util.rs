@@ -1,32 +1,86 @@
 use crate::ByteCode;
+use crate::error::{DecodeError, EncodeError};
 use alloc::{string::String, vec::Vec};
 
-pub fn read_identifier(bytes: &mut ByteCode) -> String {
-    let n = bytes.read_u8();
-    String::from_utf8(bytes.read_n(n as usize)).unwrap()
+pub fn read_identifier(bytes: &mut ByteCode) -> Result<String, DecodeError> {
+    let start = bytes.position();
+    let n = bytes.read_u8()?;
+    core::str::from_utf8(bytes.read_n(n as usize)?)
+        .map(String::from)
+        .map_err(|_| DecodeError::InvalidUtf8Identifier(bytes.location_at(start)))
 }
 
-pub fn read_variable_length_int(bytes: &mut ByteCode) -> usize {
-    match bytes.read_u8() {
-        253 => bytes.read_u16() as usize,
-        254 => bytes.read_u32() as usize,
-        255 => bytes.read_u64() as usize,
+pub fn read_variable_length_int(bytes: &mut ByteCode) -> Result<usize, DecodeError> {
+    Ok(match bytes.read_u8()? {
+        253 => bytes.read_u16()? as usize,
+        254 => bytes.read_u32()? as usize,
+        255 => bytes.read_u64()? as usize,
         f => f as usize,
-    }
+    })
 }
 
-pub fn read_programid(bytes: &mut ByteCode) -> (String, String) {
-    let (name, network) = (read_identifier(bytes), read_identifier(bytes));
-    (name, network)
+pub fn read_programid(bytes: &mut ByteCode) -> Result<(String, String), DecodeError> {
+    let (name, network) = (read_identifier(bytes)?, read_identifier(bytes)?);
+    Ok((name, network))
 }
 
-pub fn read_identifiers(bytes: &mut ByteCode) -> Vec<String> {
-    let n = bytes.read_u16();
+pub fn read_identifiers(bytes: &mut ByteCode) -> Result<Vec<String>, DecodeError> {
+    let n = bytes.read_u16()?;
     (0..n).map(|_| read_identifier(bytes)).collect()
 }
 
-pub fn read_locator(bytes: &mut ByteCode) -> (String, String, String) {
-    let (name, network) = read_programid(bytes);
-    let resource = read_identifier(bytes);
-    (name, network, resource)
+pub fn read_locator(bytes: &mut ByteCode) -> Result<(String, String, String), DecodeError> {
+    let (name, network) = read_programid(bytes)?;
+    let resource = read_identifier(bytes)?;
+    Ok((name, network, resource))
+}
+
+pub fn write_identifier(bytes: &mut Vec<u8>, identifier: &str) -> Result<(), EncodeError> {
+    let len = u8::try_from(identifier.len()).map_err(|_| EncodeError::IdentifierTooLong(String::from(identifier)))?;
+    bytes.push(len);
+    bytes.extend_from_slice(identifier.as_bytes());
+    Ok(())
+}
+
+// Number of the items that follow, in `width` little-endian bytes
+pub fn write_count(bytes: &mut Vec<u8>, count: usize, width: usize, what: &'static str) -> Result<(), EncodeError> {
+    if width < core::mem::size_of::<usize>() && count >> (8 * width) != 0 {
+        return Err(EncodeError::TooMany(what, count));
+    }
+    bytes.extend_from_slice(&count.to_le_bytes()[..width]);
+    Ok(())
+}
+
+// Shortest encoding, the same one snarkVM produces
+pub fn write_variable_length_int(bytes: &mut Vec<u8>, value: usize) {
+    match value {
+        0..=252 => bytes.push(value as u8),
+        253..=0xffff => {
+            bytes.push(253);
+            bytes.extend_from_slice(&(value as u16).to_le_bytes());
+        },
+        0x10000..=0xffff_ffff => {
+            bytes.push(254);
+            bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        },
+        _ => {
+            bytes.push(255);
+            bytes.extend_from_slice(&(value as u64).to_le_bytes());
+        },
+    }
+}
+
+pub fn write_programid(bytes: &mut Vec<u8>, (name, network): (&str, &str)) -> Result<(), EncodeError> {
+    write_identifier(bytes, name)?;
+    write_identifier(bytes, network)
+}
+
+pub fn write_identifiers(bytes: &mut Vec<u8>, identifiers: &[String]) -> Result<(), EncodeError> {
+    write_count(bytes, identifiers.len(), 2, "identifiers")?;
+    identifiers.iter().try_for_each(|identifier| write_identifier(bytes, identifier))
+}
+
+pub fn write_locator(bytes: &mut Vec<u8>, (name, network, resource): (&str, &str, &str)) -> Result<(), EncodeError> {
+    write_programid(bytes, (name, network))?;
+    write_identifier(bytes, resource)
 }
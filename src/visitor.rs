@@ -0,0 +1,329 @@
+// Traversal of the decoded program, shared by analyses (`Visitor`) and rewrites (`VisitorMut`).
+// Every method defaults to walking into its children, so implementations only override the
+// nodes they care about and call the matching `walk_*` function to keep descending.
+use crate::Disassembler;
+use crate::components::Component;
+use crate::components::commands::Command;
+use crate::components::finalize::Finalize;
+use crate::components::function::Function;
+use crate::components::instructions::{ Instruction, Operand };
+use crate::components::registers::Register;
+use crate::components::types::Literal;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Disassembler) {
+        walk_program(self, program);
+    }
+
+    fn visit_component(&mut self, component: &Component) {
+        walk_component(self, component);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_finalize(&mut self, finalize: &Finalize) {
+        walk_finalize(self, finalize);
+    }
+
+    fn visit_command(&mut self, command: &Command) {
+        walk_command(self, command);
+    }
+
+    fn visit_instruction(&mut self, instruction: &Instruction) {
+        walk_instruction(self, instruction);
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        walk_operand(self, operand);
+    }
+
+    fn visit_register(&mut self, _register: &Register) {}
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, program: &Disassembler) {
+    program.components().iter().for_each(|c| v.visit_component(c));
+}
+
+pub fn walk_component<V: Visitor + ?Sized>(v: &mut V, component: &Component) {
+    if let Component::Function(f) = component {
+        v.visit_function(f);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(v: &mut V, function: &Function) {
+    function.inputs().iter().for_each(|i| v.visit_register(i.register()));
+    function.instructions().iter().for_each(|i| v.visit_instruction(i));
+    function.outputs().iter().for_each(|o| v.visit_register(o.register()));
+    function.finalize_operands().unwrap_or_default().iter().for_each(|o| v.visit_operand(o));
+    if let Some(finalize) = function.finalize() {
+        v.visit_finalize(finalize);
+    }
+}
+
+pub fn walk_finalize<V: Visitor + ?Sized>(v: &mut V, finalize: &Finalize) {
+    finalize.inputs().iter().for_each(|i| v.visit_register(i.register()));
+    finalize.commands().iter().for_each(|c| v.visit_command(c));
+}
+
+pub fn walk_command<V: Visitor + ?Sized>(v: &mut V, command: &Command) {
+    match command {
+        Command::Instruction(i) => v.visit_instruction(i),
+        Command::Contains { key, destination, .. } | Command::Get { key, destination, .. } => {
+            v.visit_operand(key);
+            v.visit_register(destination);
+        },
+        Command::GetOrUse { key, default, destination, .. } => {
+            v.visit_operand(key);
+            v.visit_operand(default);
+            v.visit_register(destination);
+        },
+        Command::Decrement { key, value, .. } | Command::Increment { key, value, .. } | Command::Set { key, value, .. } => {
+            v.visit_operand(key);
+            v.visit_operand(value);
+        },
+        Command::Remove { key, .. } => v.visit_operand(key),
+    }
+}
+
+pub fn walk_instruction<V: Visitor + ?Sized>(v: &mut V, instruction: &Instruction) {
+    instruction.operands().iter().for_each(|o| v.visit_operand(o));
+    instruction.destinations().into_iter().for_each(|r| v.visit_register(r));
+}
+
+pub fn walk_operand<V: Visitor + ?Sized>(v: &mut V, operand: &Operand) {
+    match operand {
+        Operand::Register(r) => v.visit_register(r),
+        Operand::Literal(l) => v.visit_literal(l),
+        Operand::ProgramId(_) | Operand::Caller => {},
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Disassembler) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_component_mut(&mut self, component: &mut Component) {
+        walk_component_mut(self, component);
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_finalize_mut(&mut self, finalize: &mut Finalize) {
+        walk_finalize_mut(self, finalize);
+    }
+
+    fn visit_command_mut(&mut self, command: &mut Command) {
+        walk_command_mut(self, command);
+    }
+
+    fn visit_instruction_mut(&mut self, instruction: &mut Instruction) {
+        walk_instruction_mut(self, instruction);
+    }
+
+    fn visit_operand_mut(&mut self, operand: &mut Operand) {
+        walk_operand_mut(self, operand);
+    }
+
+    fn visit_register_mut(&mut self, _register: &mut Register) {}
+
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(v: &mut V, program: &mut Disassembler) {
+    program.components_mut().iter_mut().for_each(|c| v.visit_component_mut(c));
+    // Components may have been replaced by ones of another kind
+    program.reindex();
+}
+
+pub fn walk_component_mut<V: VisitorMut + ?Sized>(v: &mut V, component: &mut Component) {
+    if let Component::Function(f) = component {
+        v.visit_function_mut(f);
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(v: &mut V, function: &mut Function) {
+    function.inputs_mut().iter_mut().for_each(|i| v.visit_register_mut(i.register_mut()));
+    function.instructions_mut().iter_mut().for_each(|i| v.visit_instruction_mut(i));
+    function.outputs_mut().iter_mut().for_each(|o| v.visit_register_mut(o.register_mut()));
+    if let Some((operands, finalize)) = function.finalize_mut() {
+        operands.iter_mut().for_each(|o| v.visit_operand_mut(o));
+        v.visit_finalize_mut(finalize);
+    }
+}
+
+pub fn walk_finalize_mut<V: VisitorMut + ?Sized>(v: &mut V, finalize: &mut Finalize) {
+    finalize.inputs_mut().iter_mut().for_each(|i| v.visit_register_mut(i.register_mut()));
+    finalize.commands_mut().iter_mut().for_each(|c| v.visit_command_mut(c));
+}
+
+pub fn walk_command_mut<V: VisitorMut + ?Sized>(v: &mut V, command: &mut Command) {
+    match command {
+        Command::Instruction(i) => v.visit_instruction_mut(i),
+        Command::Contains { key, destination, .. } | Command::Get { key, destination, .. } => {
+            v.visit_operand_mut(key);
+            v.visit_register_mut(destination);
+        },
+        Command::GetOrUse { key, default, destination, .. } => {
+            v.visit_operand_mut(key);
+            v.visit_operand_mut(default);
+            v.visit_register_mut(destination);
+        },
+        Command::Decrement { key, value, .. } | Command::Increment { key, value, .. } | Command::Set { key, value, .. } => {
+            v.visit_operand_mut(key);
+            v.visit_operand_mut(value);
+        },
+        Command::Remove { key, .. } => v.visit_operand_mut(key),
+    }
+}
+
+pub fn walk_instruction_mut<V: VisitorMut + ?Sized>(v: &mut V, instruction: &mut Instruction) {
+    instruction.operands_mut().iter_mut().for_each(|o| v.visit_operand_mut(o));
+    instruction.destinations_mut().into_iter().for_each(|r| v.visit_register_mut(r));
+}
+
+pub fn walk_operand_mut<V: VisitorMut + ?Sized>(v: &mut V, operand: &mut Operand) {
+    match operand {
+        Operand::Register(r) => v.visit_register_mut(r),
+        Operand::Literal(l) => v.visit_literal_mut(l),
+        Operand::ProgramId(_) | Operand::Caller => {},
+    }
+}
+
+// Transformation of the whole program, usually a `VisitorMut` running itself over it
+pub trait Pass {
+    fn name(&self) -> &str;
+
+    fn run(&mut self, program: &mut Disassembler);
+}
+
+// Runs passes in the order they were added, e.g. to rewrite a program before printing it
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<P: Pass + 'static>(&mut self, pass: P) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    // Names of the passes, in the order they run
+    pub fn passes(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|p| p.name())
+    }
+
+    pub fn run(&mut self, program: &mut Disassembler) {
+        self.passes.iter_mut().for_each(|p| p.run(program));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::fs;
+    use super::*;
+    use crate::Assembly;
+
+    fn program() -> Disassembler {
+        let bytes = fs::read("examples/all_components/main.avm").expect("couldn't read bytecode");
+        let mut a = Disassembler::from_bytes(bytes);
+        a.disassemble().expect("malformed bytecode");
+        a
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        registers: usize,
+        literals: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_register(&mut self, _register: &Register) {
+            self.registers += 1;
+        }
+
+        fn visit_literal(&mut self, _literal: &Literal) {
+            self.literals += 1;
+        }
+    }
+
+    // Moves every register up by a fixed offset
+    struct Shift(usize);
+
+    impl VisitorMut for Shift {
+        fn visit_register_mut(&mut self, register: &mut Register) {
+            register.set_locator(register.locator() + self.0);
+        }
+    }
+
+    impl Pass for Shift {
+        fn name(&self) -> &str {
+            "shift"
+        }
+
+        fn run(&mut self, program: &mut Disassembler) {
+            self.visit_program_mut(program);
+        }
+    }
+
+    // Doubles every register, which does not commute with `Shift`
+    struct Double;
+
+    impl VisitorMut for Double {
+        fn visit_register_mut(&mut self, register: &mut Register) {
+            register.set_locator(register.locator() * 2);
+        }
+    }
+
+    impl Pass for Double {
+        fn name(&self) -> &str {
+            "double"
+        }
+
+        fn run(&mut self, program: &mut Disassembler) {
+            self.visit_program_mut(program);
+        }
+    }
+
+    #[test]
+    fn counts_registers_and_literals() {
+        let mut counter = Counter::default();
+        counter.visit_program(&program());
+        // sum_one_to_array3: 1 input, 3 x (operand + destination), 1 output
+        // hello: 5 inputs, 2 x (2 operands + destination), 2 outputs
+        assert_eq!(counter.registers, 8 + 13);
+        assert_eq!(counter.literals, 3);
+    }
+
+    #[test]
+    fn runs_passes_in_order() {
+        let mut shifted = program();
+        let mut passes = PassManager::new();
+        passes.add(Shift(1)).add(Double);
+        assert_eq!(passes.passes().collect::<std::vec::Vec<_>>(), ["shift", "double"]);
+        passes.run(&mut shifted);
+        assert_eq!(shifted.function("hello").map(|f| f.assembly()), Some(std::string::String::from("function hello:\n    \
+            input r2 as u16.public;\n    input r4 as u16.private;\n    input r6 as u32.private;\n    input r8 as u32.private;\n    \
+            input r10 as u32.private;\n    xor r2 r4 into r12;\n    add r8 r6 into r14;\n    output r12 as u16.private;\n    \
+            output r14 as u32.private;")));
+        assert_eq!(shifted.structs().count(), 1);
+
+        let mut doubled = program();
+        PassManager::new().add(Double).add(Shift(1)).run(&mut doubled);
+        assert!(doubled.assembly().contains("\n    xor r1 r3 into r11;\n    add r7 r5 into r13;\n"));
+    }
+}
@@ -0,0 +1,82 @@
+// JavaScript bindings, taking hex encoded bytecode or Aleo instructions source
+use crate::{Assembly, Disassembler};
+use alloc::{vec::Vec, string::{String, ToString}};
+use wasm_bindgen::prelude::*;
+
+// Use `wee_alloc` as the global allocator on wasm, native users keep their own
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
+fn decode_hex(bytes: &str) -> Result<Vec<u8>, JsValue> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(JsValue::from_str("hex input has an odd number of digits"));
+    }
+    (0..bytes.len())
+        .step_by(2)
+        .map(|i| {
+            bytes.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| JsValue::from_str(&format!("invalid hex digit at position {}", i)))
+        })
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn disassembled(bytes: &str) -> Result<Disassembler, JsValue> {
+    let mut a = Disassembler::from_bytes(decode_hex(bytes)?);
+    a.disassemble().map_err(|e| {
+        console_log!("failed to decode program: {}", e);
+        JsValue::from_str(&e.to_string())
+    })?;
+    for warning in a.warnings() {
+        console_log!("warning: {}", warning);
+    }
+    Ok(a)
+}
+
+fn assembled(source: &str) -> Result<Disassembler, JsValue> {
+    let a = Disassembler::from_aleo(source).map_err(|e| {
+        console_log!("failed to parse program: {}", e);
+        JsValue::from_str(&e.to_string())
+    })?;
+    for warning in a.warnings() {
+        console_log!("warning: {}", warning);
+    }
+    Ok(a)
+}
+
+#[wasm_bindgen]
+pub fn disassemble(bytes: &str) -> Result<String, JsValue> {
+    Ok(disassembled(bytes)?.assembly())
+}
+
+#[wasm_bindgen]
+pub fn decompile(bytes: &str) -> Result<String, JsValue> {
+    Ok(disassembled(bytes)?.leo())
+}
+
+// Callable interface of the program as JSON, see `Disassembler::abi`
+#[wasm_bindgen]
+pub fn abi(bytes: &str) -> Result<String, JsValue> {
+    Ok(disassembled(bytes)?.abi())
+}
+
+// Aleo instructions source to hex encoded bytecode
+#[wasm_bindgen]
+pub fn assemble(source: &str) -> Result<String, JsValue> {
+    let bytes = assembled(source)?.to_bytes().map_err(|e| {
+        console_log!("failed to encode program: {}", e);
+        JsValue::from_str(&e.to_string())
+    })?;
+    Ok(encode_hex(&bytes))
+}
+
+// Aleo instructions source to Leo
+#[wasm_bindgen]
+pub fn decompile_source(source: &str) -> Result<String, JsValue> {
+    Ok(assembled(source)?.leo())
+}